@@ -0,0 +1,247 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::{
+    bank::load_question_bank,
+    config::{AchievementRule, Config},
+    practice::PRACTICE_QUESTION_COUNT,
+    rating::rated_attempts,
+    slack::{post_message, section_block},
+    state::AppState,
+    store::{Attempt, EarnedAchievement, StoreData},
+    streaks::user_streak,
+    utils::now_unix,
+};
+
+/// The achievements every workspace gets. Config entries add to these or replace one by `id`.
+fn builtin_rules() -> Vec<AchievementRule> {
+    let rule = |id: &str, name: &str, badge: &str, kind: &str, count: u64| AchievementRule {
+        id: id.to_string(),
+        name: name.to_string(),
+        badge: badge.to_string(),
+        message: None,
+        kind: kind.to_string(),
+        count,
+        difficulty: None,
+        domain: None,
+        source: None,
+    };
+    vec![
+        rule("first-correct", "First Steps", "🎉", "correct", 1),
+        AchievementRule {
+            message: Some("{user} has answered 10 hard questions correctly! 💪".to_string()),
+            difficulty: Some("Hard".to_string()),
+            ..rule("hard-10", "Hard Hitter", "💪", "correct", 10)
+        },
+        rule("century", "Century", "💯", "attempts", 100),
+        AchievementRule {
+            message: Some("{user} aced a practice module without a single miss! ✨".to_string()),
+            ..rule("perfect-round", "Flawless", "✨", "perfect_round", PRACTICE_QUESTION_COUNT as u64)
+        },
+        rule("explorer", "Explorer", "🧭", "all_domains", 1),
+        rule("streak-7", "On Fire", "🔥", "streak", 7),
+        rule("streak-30", "Unstoppable", "🏆", "streak", 30),
+    ]
+}
+
+/// Built-in rules with the config's additions and replacements applied, in display order.
+pub fn achievement_rules(config: &Config) -> Vec<AchievementRule> {
+    let mut rules = builtin_rules();
+    for custom in &config.achievements {
+        match rules.iter_mut().find(|r| r.id == custom.id) {
+            Some(existing) => *existing = custom.clone(),
+            None => rules.push(custom.clone()),
+        }
+    }
+    rules
+}
+
+fn matches(rule: &AchievementRule, attempt: &Attempt) -> bool {
+    rule.difficulty.as_deref().is_none_or(|d| attempt.difficulty.eq_ignore_ascii_case(d))
+        && rule.domain.as_deref().is_none_or(|d| attempt.domain == d)
+        && rule.source.as_deref().is_none_or(|s| attempt.source == s)
+}
+
+/// Whether the user has met `rule`. `domains` is every domain in the bank, for `all_domains`.
+pub fn is_met(rule: &AchievementRule, data: &StoreData, user_id: &str, now: u64, domains: &BTreeSet<String>) -> bool {
+    // Retries on a channel question count once, as in ratings and standings.
+    let attempts = || rated_attempts(&data.attempts).filter(|a| a.user_id == user_id).filter(|a| matches(rule, a));
+    match rule.kind.as_str() {
+        "correct" => attempts().filter(|a| a.correct).count() as u64 >= rule.count,
+        "attempts" => attempts().count() as u64 >= rule.count,
+        "streak" => u64::from(user_streak(data, user_id, now).current) >= rule.count,
+        "all_domains" => {
+            let attempted: BTreeSet<&str> = attempts().map(|a| a.domain.as_str()).collect();
+            !domains.is_empty() && domains.iter().all(|d| attempted.contains(d.as_str()))
+        }
+        // A practice module records all of its answers with one timestamp and leaves out skipped
+        // questions, so `count` is how many answers the module needs; the built-in rule asks for
+        // every question in a full module.
+        "perfect_round" => {
+            let mut rounds: BTreeMap<u64, (u64, bool)> = BTreeMap::new();
+            for attempt in attempts().filter(|a| a.source == "practice") {
+                let round = rounds.entry(attempt.timestamp).or_insert((0, true));
+                round.0 += 1;
+                round.1 &= attempt.correct;
+            }
+            rounds.values().any(|&(answered, perfect)| perfect && answered >= rule.count)
+        }
+        _ => false,
+    }
+}
+
+/// The user's achievements, oldest first, with the rule each one came from. Achievements whose
+/// rule has since been removed from the config are left out.
+pub fn earned_achievements<'a>(
+    data: &StoreData,
+    rules: &'a [AchievementRule],
+    user_id: &str,
+) -> Vec<(&'a AchievementRule, u64)> {
+    data.achievements
+        .get(user_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|earned| rules.iter().find(|r| r.id == earned.id).map(|rule| (rule, earned.earned_at)))
+        .collect()
+}
+
+/// One mrkdwn line per earned achievement, plus how many are left, for `/sat stats` and App Home.
+pub fn achievement_lines(data: &StoreData, config: &Config, user_id: &str) -> (Vec<String>, usize) {
+    let rules = achievement_rules(config);
+    let earned = earned_achievements(data, &rules, user_id);
+    let lines = earned
+        .iter()
+        .map(|(rule, at)| format!("{} *{}* · <!date^{}^{{date_short}}|earned>", rule.badge, rule.name, at))
+        .collect();
+    (lines, rules.len() - earned.len())
+}
+
+/// Checks every rule the user hasn't earned yet in the background after a graded answer.
+pub fn track_attempt(state: &AppState, user_id: &str, channel_id: Option<&str>) {
+    if !state.config.features.achievements {
+        return;
+    }
+    let user_id = user_id.to_string();
+    let channel_id = channel_id.map(str::to_string);
+    state.spawn(move |state| async move {
+        if let Err(e) = evaluate_achievements(&state, &user_id, channel_id.as_deref()).await {
+            tracing::error!("Failed to evaluate achievements for {}: {}", user_id, e);
+        }
+    });
+}
+
+/// Awards newly met achievements and announces each one once, in `channel_id` or by DM.
+pub async fn evaluate_achievements(state: &AppState, user_id: &str, channel_id: Option<&str>) -> Result<()> {
+    let rules = achievement_rules(&state.config);
+    let pending: Vec<&AchievementRule> = state
+        .store
+        .read(|data| {
+            let earned: Vec<&str> = data.achievements.get(user_id).into_iter().flatten().map(|e| e.id.as_str()).collect();
+            rules.iter().filter(|r| !earned.contains(&r.id.as_str())).collect()
+        })
+        .await;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let domains: BTreeSet<String> = if pending.iter().any(|r| r.kind == "all_domains") {
+        load_question_bank(state).await?.into_iter().map(|q| q.domain).collect()
+    } else {
+        BTreeSet::new()
+    };
+
+    let now = now_unix();
+    let awarded: Vec<&AchievementRule> = state
+        .store
+        .update(|data| {
+            let met: Vec<&AchievementRule> = pending
+                .into_iter()
+                .filter(|rule| is_met(rule, data, user_id, now, &domains))
+                .collect();
+            // Another answer may have been evaluated in the meantime; only announce what's new.
+            let earned = data.achievements.entry(user_id.to_string()).or_default();
+            let new: Vec<&AchievementRule> =
+                met.into_iter().filter(|rule| !earned.iter().any(|e| e.id == rule.id)).collect();
+            earned.extend(new.iter().map(|rule| EarnedAchievement { id: rule.id.clone(), earned_at: now }));
+            new
+        })
+        .await?;
+
+    let token = state.config.slack.bot_token.clone();
+    let target = channel_id.filter(|c| !c.is_empty()).unwrap_or(user_id);
+    for rule in awarded {
+        let mention = format!("<@{}>", user_id);
+        let text = match &rule.message {
+            Some(message) => message.replace("{user}", &mention).replace("{name}", &rule.name),
+            None => format!("{} unlocked *{}*!", mention, rule.name),
+        };
+        post_message(&token, target, vec![section_block(&format!("{} {}", rule.badge, text))]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: &str, count: u64) -> AchievementRule {
+        builtin_rules().into_iter().find(|r| r.kind == kind && r.count == count).unwrap_or(AchievementRule {
+            id: kind.to_string(),
+            name: kind.to_string(),
+            badge: String::new(),
+            message: None,
+            kind: kind.to_string(),
+            count,
+            difficulty: None,
+            domain: None,
+            source: None,
+        })
+    }
+
+    fn met(rule: &AchievementRule, attempts: Vec<Attempt>) -> bool {
+        let data = StoreData { attempts, ..Default::default() };
+        is_met(rule, &data, "U1", 0, &BTreeSet::new())
+    }
+
+    fn module(answers: usize, correct: bool) -> Vec<Attempt> {
+        (0..answers)
+            .map(|i| {
+                let mut attempt = Attempt::test("U1", &format!("q{}", i)).correct(correct).at(1_000);
+                attempt.source = "practice".to_string();
+                attempt.channel_id = String::new();
+                attempt
+            })
+            .collect()
+    }
+
+    #[test]
+    fn retries_on_one_message_count_once() {
+        let retries: Vec<Attempt> = (0..100).map(|i| Attempt::test("U1", "q1").at(i).on_message("1.0")).collect();
+        assert!(!met(&rule("attempts", 100), retries.clone()));
+        assert!(!met(&rule("correct", 1), retries));
+
+        let correct_retry = vec![
+            Attempt::test("U1", "q1").on_message("1.0"),
+            Attempt::test("U1", "q1").correct(true).on_message("1.0"),
+        ];
+        assert!(!met(&rule("correct", 1), correct_retry));
+    }
+
+    #[test]
+    fn separate_messages_and_practice_answers_each_count() {
+        let attempts: Vec<Attempt> = (0..100).map(|i| Attempt::test("U1", "q1").on_message(&i.to_string())).collect();
+        assert!(met(&rule("attempts", 100), attempts));
+        assert!(met(&rule("correct", 1), module(1, true)));
+    }
+
+    #[test]
+    fn perfect_round_needs_a_full_module_without_misses() {
+        let full = rule("perfect_round", PRACTICE_QUESTION_COUNT as u64);
+        assert!(met(&full, module(PRACTICE_QUESTION_COUNT, true)));
+        assert!(!met(&full, module(5, true)));
+
+        let mut one_miss = module(PRACTICE_QUESTION_COUNT, true);
+        one_miss[3].selected = "B".to_string();
+        one_miss[3].correct = false;
+        assert!(!met(&full, one_miss));
+    }
+}
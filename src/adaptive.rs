@@ -0,0 +1,96 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use crate::{
+    bank::load_question_bank,
+    errors::report_failure,
+    history::{record_served, recently_seen, unseen_or_all, EXHAUSTED_MESSAGE},
+    models::*,
+    rating::{pick_adaptive, register_question, user_rating},
+    slack::{create_question_blocks, respond, section_block},
+    settings::channel_filter,
+    state::AppState,
+    metrics::metrics,
+    utils::now_unix,
+};
+
+pub async fn start_adaptive(state: &AppState, command: &SlackSlashCommand, args: &str) -> Value {
+    let domain = args.trim();
+    let domain = (!domain.is_empty()).then(|| domain.to_string());
+
+    let user_id = command.user_id.clone();
+    let channel_id = command.channel_id.clone();
+    let response_url = command.response_url.clone();
+    state.spawn(move |state| async move {
+        if let Err(e) = send_adaptive_question(&state, &user_id, &channel_id, &response_url, domain.as_deref()).await {
+            report_failure(&response_url, "finding an adaptive question", &e).await;
+        }
+    });
+
+    json!({
+        "response_type": "ephemeral",
+        "text": "🎯 Finding a question at your level..."
+    })
+}
+
+async fn send_adaptive_question(
+    state: &AppState,
+    user_id: &str,
+    channel_id: &str,
+    response_url: &str,
+    domain: Option<&str>,
+) -> Result<()> {
+    let allowed = channel_filter(state, Some(channel_id)).await;
+    let bank = load_question_bank(state).await?;
+    let now = now_unix();
+    let window = state.config.repeat_window_secs(None);
+    let picked = state
+        .store
+        .read(|data| {
+            let pool: Vec<&SATQuestion> = bank
+                .iter()
+                .filter(|q| allowed(q))
+                .filter(|q| domain.is_none_or(|d| q.domain.eq_ignore_ascii_case(d)))
+                .collect();
+            let (candidates, exhausted) = unseen_or_all(&pool, &recently_seen(data, Some(user_id), None, now, window));
+            pick_adaptive(data, candidates, user_id, domain)
+                .map(|q| (q.clone(), user_rating(data, user_id, &q.domain).rating, exhausted))
+        })
+        .await;
+
+    let Some((question, rating, exhausted)) = picked else {
+        return respond(response_url, &json!({
+            "response_type": "ephemeral",
+            "replace_original": true,
+            "text": format!("No questions found for domain `{}`.", domain.unwrap_or_default()),
+        }))
+        .await;
+    };
+
+    state
+        .store
+        .update(|data| {
+            register_question(data, &question);
+            record_served(data, Some(user_id), None, &[question.id.as_str()], now, window);
+        })
+        .await?;
+
+    let mut blocks = Vec::new();
+    if exhausted {
+        blocks.push(section_block(EXHAUSTED_MESSAGE));
+    }
+    blocks.push(section_block(&format!(
+        "🎯 *Adaptive practice* — your *{}* rating is {:.0}",
+        question.domain, rating
+    )));
+    blocks.extend(create_question_blocks(&question, state.config.features.hints));
+
+    respond(response_url, &json!({
+        "response_type": "ephemeral",
+        "replace_original": true,
+        "text": "Adaptive practice question",
+        "blocks": blocks,
+    }))
+    .await?;
+    metrics().question_posted("adaptive");
+    Ok(())
+}
@@ -0,0 +1,352 @@
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use crate::{
+    store::{Attempt, StoreData},
+    utils::parse_date,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+/// Share of responders in each of the upper and lower groups for the discrimination index.
+const DISCRIMINATION_GROUP: f64 = 0.27;
+/// Below this many responses an item's statistics are too noisy to flag or discriminate on.
+const MIN_RESPONSES: usize = 10;
+const CHOICES: [&str; 4] = ["A", "B", "C", "D"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Which attempts an export covers, and in what format. Shared by the HTTP endpoints (query
+/// parameters) and the CLI (`--flag value`), which take the same names.
+#[derive(Debug, Clone)]
+pub struct ExportQuery {
+    pub channel_id: Option<String>,
+    pub user_id: Option<String>,
+    pub domain: Option<String>,
+    /// Unix seconds, inclusive.
+    pub from: Option<u64>,
+    /// Unix seconds, exclusive.
+    pub to: Option<u64>,
+    pub format: ExportFormat,
+}
+
+impl ExportQuery {
+    /// Reads `channel`, `user`, `domain`, `from`, `to` (YYYY-MM-DD, inclusive, or unix seconds)
+    /// and `format` (csv or json, default csv). Unknown names are an error so typos don't
+    /// silently widen an export.
+    pub fn parse<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        let mut query = Self {
+            channel_id: None,
+            user_id: None,
+            domain: None,
+            from: None,
+            to: None,
+            format: ExportFormat::Csv,
+        };
+        for (name, value) in pairs {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match name {
+                "channel" => query.channel_id = Some(value.to_string()),
+                "user" => query.user_id = Some(value.to_string()),
+                "domain" => query.domain = Some(value.to_string()),
+                "from" => query.from = Some(parse_bound(name, value, 0)?),
+                "to" => query.to = Some(parse_bound(name, value, DAY)?),
+                "format" => {
+                    query.format = ExportFormat::parse(value).ok_or_else(|| anyhow!("unknown format `{}`; use csv or json", value))?
+                }
+                other => bail!("unknown filter `{}`; use channel, user, domain, from, to or format", other),
+            }
+        }
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if to <= from {
+                bail!("`to` has to be on or after `from`");
+            }
+        }
+        Ok(query)
+    }
+
+    pub fn matches(&self, attempt: &Attempt) -> bool {
+        self.channel_id.as_deref().is_none_or(|c| attempt.channel_id == c)
+            && self.user_id.as_deref().is_none_or(|u| attempt.user_id == u)
+            && self.domain.as_deref().is_none_or(|d| attempt.domain.eq_ignore_ascii_case(d))
+            && self.from.is_none_or(|from| attempt.timestamp >= from)
+            && self.to.is_none_or(|to| attempt.timestamp < to)
+    }
+}
+
+/// A date covers its whole day, so `to` dates get `day_end` added.
+fn parse_bound(name: &str, value: &str, day_end: u64) -> Result<u64> {
+    if let Some(day) = parse_date(value) {
+        return Ok(day + day_end);
+    }
+    value
+        .parse()
+        .map_err(|_| anyhow!("`{}` must be YYYY-MM-DD or unix seconds, got `{}`", name, value))
+}
+
+pub fn filter_attempts<'a>(data: &'a StoreData, query: &ExportQuery) -> Vec<&'a Attempt> {
+    data.attempts.iter().filter(|a| query.matches(a)).collect()
+}
+
+pub fn export_attempts(attempts: &[&Attempt], format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_vec_pretty(attempts)?),
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record([
+                "user_id", "channel_id", "question_id", "domain", "difficulty", "selected", "correct", "timestamp", "source",
+                "message_ts", "hints",
+            ])?;
+            for a in attempts {
+                writer.write_record([
+                    a.user_id.as_str(),
+                    &a.channel_id,
+                    &a.question_id,
+                    &a.domain,
+                    &a.difficulty,
+                    &a.selected,
+                    if a.correct { "true" } else { "false" },
+                    &a.timestamp.to_string(),
+                    &a.source,
+                    a.message_ts.as_deref().unwrap_or_default(),
+                    &a.hints.to_string(),
+                ])?;
+            }
+            Ok(writer.into_inner()?)
+        }
+    }
+}
+
+/// Classical test statistics for one question, from each user's first response to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemStats {
+    pub question_id: String,
+    pub domain: String,
+    pub difficulty: String,
+    pub responses: usize,
+    pub correct: usize,
+    /// Share answering correctly; higher is easier.
+    pub p_value: f64,
+    /// Upper-group minus lower-group p-value, grouping responders by their overall accuracy.
+    /// `None` below [`MIN_RESPONSES`].
+    pub discrimination: Option<f64>,
+    /// Share of responses picking each choice.
+    pub choices: BTreeMap<String, f64>,
+    /// What looks off about the item, if anything.
+    pub flag: Option<String>,
+}
+
+/// Per-question statistics over `attempts`, by question id. Only a user's first attempt at a
+/// question counts, so retries after seeing the answer don't inflate the p-value.
+pub fn item_stats(attempts: &[&Attempt]) -> Vec<ItemStats> {
+    let mut first: BTreeMap<(&str, &str), &Attempt> = BTreeMap::new();
+    for attempt in attempts {
+        let key = (attempt.question_id.as_str(), attempt.user_id.as_str());
+        first
+            .entry(key)
+            .and_modify(|seen| {
+                if attempt.timestamp < seen.timestamp {
+                    *seen = attempt;
+                }
+            })
+            .or_insert(attempt);
+    }
+
+    // Each user's accuracy across every item, to rank them for the discrimination index.
+    let mut totals: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for attempt in first.values() {
+        let total = totals.entry(attempt.user_id.as_str()).or_default();
+        total.0 += 1;
+        total.1 += usize::from(attempt.correct);
+    }
+    let score = |user: &str| totals.get(user).map(|&(n, c)| c as f64 / n as f64).unwrap_or(0.0);
+
+    let mut by_question: BTreeMap<&str, Vec<&Attempt>> = BTreeMap::new();
+    for ((question_id, _), attempt) in &first {
+        by_question.entry(question_id).or_default().push(attempt);
+    }
+
+    by_question
+        .into_iter()
+        .map(|(question_id, mut responses)| {
+            let n = responses.len();
+            let correct = responses.iter().filter(|a| a.correct).count();
+            let p_value = correct as f64 / n as f64;
+
+            let discrimination = (n >= MIN_RESPONSES).then(|| {
+                responses.sort_by(|a, b| score(&b.user_id).total_cmp(&score(&a.user_id)));
+                let group = ((n as f64 * DISCRIMINATION_GROUP).round() as usize).max(1);
+                let p = |group: &[&Attempt]| group.iter().filter(|a| a.correct).count() as f64 / group.len() as f64;
+                p(&responses[..group]) - p(&responses[n - group..])
+            });
+
+            let mut picks: BTreeMap<String, usize> = CHOICES.iter().map(|c| (c.to_string(), 0)).collect();
+            for response in &responses {
+                *picks.entry(response.selected.to_uppercase()).or_default() += 1;
+            }
+            let choices = picks.into_iter().map(|(choice, count)| (choice, count as f64 / n as f64)).collect();
+
+            let flag = if n < MIN_RESPONSES {
+                None
+            } else if discrimination.is_some_and(|d| d < 0.0) {
+                Some("negative discrimination: check the answer key".to_string())
+            } else if p_value > 0.9 {
+                Some("too easy".to_string())
+            } else if p_value < 0.2 {
+                Some("too hard or miskeyed".to_string())
+            } else if discrimination.is_some_and(|d| d < 0.2) {
+                Some("weak discrimination".to_string())
+            } else {
+                None
+            };
+
+            let sample = responses[0];
+            ItemStats {
+                question_id: question_id.to_string(),
+                domain: sample.domain.clone(),
+                difficulty: sample.difficulty.clone(),
+                responses: n,
+                correct,
+                p_value,
+                discrimination,
+                choices,
+                flag,
+            }
+        })
+        .collect()
+}
+
+pub fn export_item_stats(items: &[ItemStats], format: ExportFormat) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_vec_pretty(items)?),
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            let mut header = vec!["question_id", "domain", "difficulty", "responses", "correct", "p_value", "discrimination"];
+            let choice_columns: Vec<String> = CHOICES.iter().map(|c| format!("choice_{}", c.to_lowercase())).collect();
+            header.extend(choice_columns.iter().map(String::as_str));
+            header.push("flag");
+            writer.write_record(&header)?;
+
+            for item in items {
+                let mut row = vec![
+                    item.question_id.clone(),
+                    item.domain.clone(),
+                    item.difficulty.clone(),
+                    item.responses.to_string(),
+                    item.correct.to_string(),
+                    format!("{:.3}", item.p_value),
+                    item.discrimination.map(|d| format!("{:.3}", d)).unwrap_or_default(),
+                ];
+                row.extend(CHOICES.iter().map(|c| format!("{:.3}", item.choices.get(*c).copied().unwrap_or(0.0))));
+                row.push(item.flag.clone().unwrap_or_default());
+                writer.write_record(&row)?;
+            }
+            Ok(writer.into_inner()?)
+        }
+    }
+}
+
+/// Compares an API token without bailing out at the first differing byte.
+pub fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() && expected.bytes().zip(given.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    fn attempt(user: usize, question_id: &str, correct: bool, timestamp: u64) -> Attempt {
+        Attempt::test(&format!("U{}", user), question_id).correct(correct).at(timestamp)
+    }
+
+    fn stats(attempts: &[Attempt]) -> Vec<ItemStats> {
+        item_stats(&attempts.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn p_value_and_choice_shares() {
+        let attempts: Vec<Attempt> = (0..4).map(|u| attempt(u, "q1", u == 0, 100)).collect();
+        let items = stats(&attempts);
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!((item.responses, item.correct), (4, 1));
+        assert!(approx_eq(item.p_value, 0.25));
+        assert!(approx_eq(item.choices["A"], 0.25));
+        assert!(approx_eq(item.choices["B"], 0.75));
+        assert!(approx_eq(item.choices["C"], 0.0));
+        // Too few responses to judge the item.
+        assert_eq!(item.discrimination, None);
+        assert_eq!(item.flag, None);
+    }
+
+    #[test]
+    fn only_first_attempts_count() {
+        let attempts = [attempt(0, "q1", true, 200), attempt(0, "q1", false, 100), attempt(1, "q1", false, 100)];
+        let item = &stats(&attempts)[0];
+        assert_eq!((item.responses, item.correct), (2, 0));
+    }
+
+    #[test]
+    fn strong_items_discriminate_positively() {
+        let attempts: Vec<Attempt> = (0..10).map(|u| attempt(u, "q1", u < 6, 100)).collect();
+        let item = &stats(&attempts)[0];
+        assert!(approx_eq(item.p_value, 0.6));
+        assert!(approx_eq(item.discrimination.unwrap(), 1.0));
+        assert_eq!(item.flag, None);
+    }
+
+    #[test]
+    fn miskeyed_items_discriminate_negatively() {
+        // Users 0-4 do well overall but miss q1; users 5-9 do the opposite.
+        let mut attempts = Vec::new();
+        for u in 0..10 {
+            let strong = u < 5;
+            attempts.push(attempt(u, "q1", !strong, 100));
+            attempts.push(attempt(u, "q2", strong, 100));
+            attempts.push(attempt(u, "q3", strong, 100));
+        }
+        let items = stats(&attempts);
+        let q1 = items.iter().find(|i| i.question_id == "q1").unwrap();
+        assert!(approx_eq(q1.discrimination.unwrap(), -1.0));
+        assert_eq!(q1.flag.as_deref(), Some("negative discrimination: check the answer key"));
+    }
+
+    #[test]
+    fn flags_items_that_are_too_easy_or_too_hard() {
+        let easy: Vec<Attempt> = (0..10).map(|u| attempt(u, "q1", true, 100)).collect();
+        assert_eq!(stats(&easy)[0].flag.as_deref(), Some("too easy"));
+        let hard: Vec<Attempt> = (0..10).map(|u| attempt(u, "q1", u == 0, 100)).collect();
+        assert_eq!(stats(&hard)[0].flag.as_deref(), Some("too hard or miskeyed"));
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use crate::{
+    models::*,
+    slack::{
+        create_question_content_blocks, mrkdwn_block, post_json_message, post_message, respond, respond_ephemeral,
+        section_block, slack_api,
+    },
+    state::AppState,
+    store::CustomQuestion,
+    utils::{format_text_for_slack, generate_id, now_unix},
+};
+
+pub const AUTHOR_CALLBACK_ID: &str = "question_author";
+pub const AUTHOR_PREVIEW_CALLBACK_ID: &str = "question_author_preview";
+
+const LETTERS: [&str; 4] = ["A", "B", "C", "D"];
+/// Drafts older than this are dropped, in case Slack never tells us the preview was closed.
+const DRAFT_TTL_SECS: u64 = 60 * 60;
+const DIFFICULTIES: [&str; 3] = ["Easy", "Medium", "Hard"];
+
+/// A question between the authoring form and the preview's Save button.
+#[derive(Debug, Clone)]
+pub struct AuthorDraft {
+    pub question: SATQuestion,
+    pub created_at: u64,
+}
+
+pub async fn open_author_modal(state: &AppState, trigger_id: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let option = |value: &str| json!({ "text": { "type": "plain_text", "text": value }, "value": value });
+
+    let mut blocks = vec![
+        text_input("paragraph", "Paragraph (optional)", true, true),
+        text_input("question", "Question", false, true),
+    ];
+    for letter in LETTERS {
+        blocks.push(text_input(&format!("choice_{}", letter.to_lowercase()), &format!("Choice {}", letter), false, false));
+    }
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "correct_answer",
+        "label": { "type": "plain_text", "text": "Correct answer" },
+        "element": {
+            "type": "static_select",
+            "action_id": "select",
+            "options": LETTERS.iter().map(|l| option(l)).collect::<Vec<_>>(),
+        }
+    }));
+    blocks.push(text_input("explanation", "Explanation", false, true));
+    blocks.push(text_input("domain", "Domain", false, false));
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "difficulty",
+        "label": { "type": "plain_text", "text": "Difficulty" },
+        "element": {
+            "type": "static_select",
+            "action_id": "select",
+            "options": DIFFICULTIES.iter().map(|d| option(d)).collect::<Vec<_>>(),
+        }
+    }));
+
+    slack_api(&token, "views.open", &json!({
+        "trigger_id": trigger_id,
+        "view": {
+            "type": "modal",
+            "callback_id": AUTHOR_CALLBACK_ID,
+            "title": { "type": "plain_text", "text": "Write a question" },
+            "submit": { "type": "plain_text", "text": "Preview" },
+            "close": { "type": "plain_text", "text": "Cancel" },
+            "blocks": blocks,
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Validates the form and pushes a preview. Runs inline because Slack expects the
+/// `response_action` in the HTTP response.
+pub async fn handle_author_submission(state: &AppState, interaction: &SlackInteraction) -> Value {
+    let Some(view) = &interaction.view else {
+        return json!({});
+    };
+    let field = |block_id: &str| view.input_value(block_id).map(|v| v.trim().to_string()).unwrap_or_default();
+
+    let mut errors = Map::new();
+    for block_id in ["question", "choice_a", "choice_b", "choice_c", "choice_d", "explanation", "domain"] {
+        if field(block_id).is_empty() {
+            errors.insert(block_id.to_string(), json!("This field is required."));
+        }
+    }
+    if !LETTERS.contains(&field("correct_answer").as_str()) {
+        errors.insert("correct_answer".to_string(), json!("Pick the correct answer."));
+    }
+    if !errors.is_empty() {
+        return json!({ "response_action": "errors", "errors": errors });
+    }
+
+    let paragraph = field("paragraph");
+    let question = SATQuestion {
+        id: format!("custom-{}", generate_id()),
+        domain: field("domain"),
+        visuals: None,
+        question: Question {
+            paragraph: (!paragraph.is_empty()).then_some(paragraph),
+            question: field("question"),
+            choices: Choices {
+                a: field("choice_a"),
+                b: field("choice_b"),
+                c: field("choice_c"),
+                d: field("choice_d"),
+            },
+            correct_answer: field("correct_answer"),
+            explanation: field("explanation"),
+        },
+        difficulty: field("difficulty"),
+    };
+
+    let draft_id = generate_id();
+    let blocks = preview_blocks(&question);
+    let now = now_unix();
+    let mut drafts = state.author_drafts.lock().await;
+    drafts.retain(|_, draft| now.saturating_sub(draft.created_at) < DRAFT_TTL_SECS);
+    drafts.insert(draft_id.clone(), AuthorDraft { question, created_at: now });
+    drop(drafts);
+
+    json!({
+        "response_action": "push",
+        "view": {
+            "type": "modal",
+            "callback_id": AUTHOR_PREVIEW_CALLBACK_ID,
+            "private_metadata": draft_id,
+            "title": { "type": "plain_text", "text": "Preview" },
+            "submit": { "type": "plain_text", "text": "Save" },
+            "close": { "type": "plain_text", "text": "Back" },
+            "notify_on_close": true,
+            "blocks": blocks,
+        }
+    })
+}
+
+/// Saves the previewed draft. Admins publish directly; everyone else waits for approval.
+pub async fn handle_author_preview_submission(state: &AppState, interaction: &SlackInteraction) -> Result<()> {
+    let Some(view) = &interaction.view else {
+        return Ok(());
+    };
+    let Some(AuthorDraft { question, .. }) = state.author_drafts.lock().await.remove(&view.private_metadata) else {
+        return Ok(());
+    };
+
+    let author = interaction.user.id.clone();
+    let approved = state.config.is_admin(&author);
+    let custom = CustomQuestion {
+        question: question.clone(),
+        author: author.clone(),
+        status: if approved { "approved" } else { "pending" }.to_string(),
+        created_at: now_unix(),
+        reviewed_by: approved.then(|| author.clone()),
+    };
+    state.store.update(|data| data.custom_questions.push(custom)).await?;
+
+    let token = state.config.slack.bot_token.clone();
+    let confirmation = if approved {
+        format!("✅ Your question `{}` was added to the question bank.", question.id)
+    } else {
+        format!("📝 Thanks! Your question `{}` was submitted and will be added once an admin approves it.", question.id)
+    };
+    post_message(&token, &author, vec![section_block(&confirmation)]).await?;
+
+    if !approved {
+        match &state.config.moderation.channel {
+            Some(channel) => {
+                let mut blocks = vec![mrkdwn_block(&format!("*📝 New question from <@{}> awaiting approval*", author))];
+                blocks.extend(preview_blocks(&question));
+                blocks.push(review_buttons(&question.id));
+                post_json_message(&token, &json!({
+                    "channel": channel,
+                    "text": "New question awaiting approval",
+                    "blocks": blocks,
+                }))
+                .await?;
+            }
+            None => tracing::warn!("No moderator channel is configured; question {} awaits approval", question.id),
+        }
+    }
+    Ok(())
+}
+
+/// The preview was closed with Back or the modal was dismissed: forget the draft.
+pub async fn discard_author_draft(state: &AppState, interaction: &SlackInteraction) {
+    if let Some(view) = &interaction.view {
+        state.author_drafts.lock().await.remove(&view.private_metadata);
+    }
+}
+
+pub async fn handle_author_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    if !state.config.is_admin(&interaction.user.id) {
+        return respond_ephemeral(&interaction.response_url, "Only configured admins can approve questions.").await;
+    }
+
+    let question_id = action.value.clone().unwrap_or_default();
+    let status = match action.action_id.as_str() {
+        "author_approve" => "approved",
+        "author_reject" => "rejected",
+        other => {
+            tracing::warn!("Unknown authoring action: {}", other);
+            return Ok(());
+        }
+    };
+    let reviewer = interaction.user.id.clone();
+
+    let reviewed = state
+        .store
+        .update(|data| {
+            let custom = data.custom_questions.iter_mut().find(|c| c.question.id == question_id)?;
+            custom.status = status.to_string();
+            custom.reviewed_by = Some(reviewer.clone());
+            Some(custom.clone())
+        })
+        .await?;
+    let Some(custom) = reviewed else {
+        return respond_ephemeral(&interaction.response_url, "That question no longer exists.").await;
+    };
+
+    let verdict = if status == "approved" {
+        format!("✅ Approved by <@{}>", reviewer)
+    } else {
+        format!("🚫 Rejected by <@{}>", reviewer)
+    };
+    let mut blocks = vec![mrkdwn_block(&format!("*📝 Question from <@{}>*", custom.author))];
+    blocks.extend(preview_blocks(&custom.question));
+    blocks.push(mrkdwn_block(&verdict));
+    respond(&interaction.response_url, &json!({
+        "replace_original": true,
+        "text": verdict,
+        "blocks": blocks,
+    }))
+    .await?;
+
+    let token = state.config.slack.bot_token.clone();
+    let notice = format!(
+        "Your question `{}` was {}.",
+        custom.question.id,
+        if status == "approved" { "approved and added to the question bank 🎉" } else { "not approved" }
+    );
+    post_message(&token, &custom.author, vec![section_block(&notice)]).await?;
+    Ok(())
+}
+
+/// The question as it will appear in Slack, followed by the answer key and explanation.
+fn preview_blocks(question: &SATQuestion) -> Vec<Value> {
+    let mut blocks: Vec<Value> = create_question_content_blocks(question)
+        .into_iter()
+        .map(|b| serde_json::to_value(b).unwrap_or_default())
+        .collect();
+
+    let choices = [
+        ("A", &question.question.choices.a),
+        ("B", &question.question.choices.b),
+        ("C", &question.question.choices.c),
+        ("D", &question.question.choices.d),
+    ]
+    .iter()
+    .map(|(letter, text)| {
+        let marker = if *letter == question.question.correct_answer { "✅" } else { "▫️" };
+        format!("{} {}. {}", marker, letter, format_text_for_slack(text))
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+
+    blocks.push(mrkdwn_block(&choices));
+    blocks.push(mrkdwn_block(&format!(
+        "*Explanation:* {}",
+        format_text_for_slack(&question.question.explanation)
+    )));
+    blocks
+}
+
+fn review_buttons(question_id: &str) -> Value {
+    json!({
+        "type": "actions",
+        "elements": [
+            {
+                "type": "button",
+                "text": { "type": "plain_text", "text": "✅ Approve", "emoji": true },
+                "style": "primary",
+                "action_id": "author_approve",
+                "value": question_id,
+            },
+            {
+                "type": "button",
+                "text": { "type": "plain_text", "text": "🚫 Reject", "emoji": true },
+                "style": "danger",
+                "action_id": "author_reject",
+                "value": question_id,
+            }
+        ]
+    })
+}
+
+fn text_input(block_id: &str, label: &str, optional: bool, multiline: bool) -> Value {
+    json!({
+        "type": "input",
+        "block_id": block_id,
+        "optional": optional,
+        "label": { "type": "plain_text", "text": label },
+        "element": {
+            "type": "plain_text_input",
+            "action_id": "input",
+            "multiline": multiline,
+        }
+    })
+}
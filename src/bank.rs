@@ -0,0 +1,57 @@
+use anyhow::Result;
+use std::{collections::HashMap, sync::atomic::Ordering};
+use crate::{
+    lint::exclude_failing,
+    metrics::metrics,
+    models::SATQuestion,
+    slack::fetch_question_bank,
+    state::AppState,
+    store::QuestionOverride,
+};
+
+/// The bank served to users: [`load_full_bank`], minus questions that fail lint when
+/// the `lint_exclude` feature is on.
+pub async fn load_question_bank(state: &AppState) -> Result<Vec<SATQuestion>> {
+    let bank = load_full_bank(state).await?;
+    Ok(if state.config.features.lint_exclude { exclude_failing(bank) } else { bank })
+}
+
+/// Fetches the upstream bank, adds approved tutor-written questions and applies moderator
+/// overrides: hidden questions are dropped and corrected answer keys replace the upstream ones.
+pub async fn load_full_bank(state: &AppState) -> Result<Vec<SATQuestion>> {
+    let mut bank = fetch_question_bank(&state.config).await?;
+    let (custom, overrides) = state
+        .store
+        .read(|data| {
+            let custom: Vec<SATQuestion> = data
+                .custom_questions
+                .iter()
+                .filter(|c| c.status == "approved")
+                .map(|c| c.question.clone())
+                .collect();
+            (custom, data.question_overrides.clone())
+        })
+        .await;
+    bank.extend(custom);
+    let bank = apply_overrides(bank, &overrides);
+    metrics().bank_size.set(bank.len() as i64);
+    state.bank_loaded.store(true, Ordering::Relaxed);
+    Ok(bank)
+}
+
+pub fn apply_overrides(bank: Vec<SATQuestion>, overrides: &HashMap<String, QuestionOverride>) -> Vec<SATQuestion> {
+    bank.into_iter()
+        .filter_map(|mut question| {
+            let Some(change) = overrides.get(&question.id) else {
+                return Some(question);
+            };
+            if change.hidden {
+                return None;
+            }
+            if let Some(answer) = &change.correct_answer {
+                question.question.correct_answer = answer.clone();
+            }
+            Some(question)
+        })
+        .collect()
+}
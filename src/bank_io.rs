@@ -0,0 +1,614 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::{collections::HashMap, fmt, fs, path::Path};
+use crate::{
+    lint::{lint_question, Severity},
+    models::*,
+};
+
+const LETTERS: [&str; 4] = ["A", "B", "C", "D"];
+const CSV_HEADER: [&str; 13] = [
+    "id",
+    "domain",
+    "difficulty",
+    "paragraph",
+    "question",
+    "choice_a",
+    "choice_b",
+    "choice_c",
+    "choice_d",
+    "correct_answer",
+    "explanation",
+    "visual_type",
+    "svg_content",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BankFormat {
+    Jsonl,
+    Csv,
+    /// IMS QTI 2.1, one `assessmentItem` per `.xml` file.
+    Qti,
+}
+
+impl BankFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            "csv" => Some(Self::Csv),
+            "qti" | "xml" => Some(Self::Qti),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the file extension; directories are treated as QTI item folders.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return Some(Self::Qti);
+        }
+        path.extension().and_then(|e| e.to_str()).and_then(Self::parse)
+    }
+}
+
+/// A problem with one imported item, pointing at the file and line it came from.
+#[derive(Debug, Clone)]
+pub struct ImportError {
+    pub source: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.source, self.line, self.message)
+    }
+}
+
+/// Reads every item from `path` and validates it. Items that fail to parse or validate are
+/// reported instead of returned, so a clean import has an empty error list. An id used more than
+/// once is reported at every repeat, since only one of the items could be kept.
+pub fn import_questions(path: &Path, format: BankFormat) -> Result<(Vec<SATQuestion>, Vec<ImportError>)> {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+
+    if format == BankFormat::Qti && path.is_dir() {
+        let mut files: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("xml")))
+            .collect();
+        files.sort();
+        for file in files {
+            let contents = fs::read_to_string(&file).with_context(|| format!("reading {}", file.display()))?;
+            parse_qti(&file.display().to_string(), &contents, &mut parsed, &mut errors);
+        }
+    } else {
+        let source = path.display().to_string();
+        let contents = fs::read_to_string(path).with_context(|| format!("reading {}", source))?;
+        match format {
+            BankFormat::Jsonl => parse_jsonl(&source, &contents, &mut parsed, &mut errors),
+            BankFormat::Csv => parse_csv(&source, &contents, &mut parsed, &mut errors),
+            BankFormat::Qti => parse_qti(&source, &contents, &mut parsed, &mut errors),
+        }
+    }
+
+    let mut questions = Vec::new();
+    let mut first_seen: HashMap<String, (String, usize)> = HashMap::new();
+    for (source, line, question) in parsed {
+        if let Some((first_source, first_line)) = first_seen.get(&question.id) {
+            errors.push(ImportError {
+                source,
+                line,
+                message: format!("{}: duplicate id, first used at {}:{}", question.id, first_source, first_line),
+            });
+            continue;
+        }
+        first_seen.insert(question.id.clone(), (source.clone(), line));
+        let problems = validate_question(&question);
+        if problems.is_empty() {
+            questions.push(question);
+        } else {
+            errors.extend(problems.into_iter().map(|message| ImportError {
+                source: source.clone(),
+                line,
+                message: format!("{}: {}", question.id, message),
+            }));
+        }
+    }
+    Ok((questions, errors))
+}
+
+/// Writes the questions to `path`. QTI export writes one `<id>.xml` file per item into a directory.
+pub fn export_questions(path: &Path, format: BankFormat, questions: &[SATQuestion]) -> Result<()> {
+    match format {
+        BankFormat::Jsonl => {
+            let mut out = String::new();
+            for question in questions {
+                out.push_str(&serde_json::to_string(question)?);
+                out.push('\n');
+            }
+            fs::write(path, out)?;
+        }
+        BankFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(CSV_HEADER)?;
+            for q in questions {
+                let visuals = q.visuals.clone().unwrap_or_default();
+                writer.write_record([
+                    q.id.as_str(),
+                    &q.domain,
+                    &q.difficulty,
+                    q.question.paragraph.as_deref().unwrap_or_default(),
+                    &q.question.question,
+                    &q.question.choices.a,
+                    &q.question.choices.b,
+                    &q.question.choices.c,
+                    &q.question.choices.d,
+                    &q.question.correct_answer,
+                    &q.question.explanation,
+                    visuals.visual_type.as_deref().unwrap_or_default(),
+                    visuals.svg_content.as_deref().unwrap_or_default(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        BankFormat::Qti => {
+            fs::create_dir_all(path)?;
+            for question in questions {
+                let file = path.join(format!("{}.xml", sanitize_file_name(&question.id)));
+                fs::write(file, qti_item(question))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The error-level lint findings for a question: anything that would break it in Slack.
+pub fn validate_question(question: &SATQuestion) -> Vec<String> {
+    lint_question(question)
+        .into_iter()
+        .filter(|f| f.severity == Severity::Error)
+        .map(|f| format!("[{}] {}", f.rule, f.message))
+        .collect()
+}
+
+/// Verifies that `\(`/`\)` and `\[`/`\]` delimiters pair up and braces balance inside them.
+pub fn check_latex(text: &str) -> std::result::Result<(), String> {
+    let mut open: Option<char> = None;
+    let mut depth = 0i32;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(d @ ('(' | '[')) => {
+                    if open.is_some() {
+                        return Err(format!("`\\{}` opened inside another math span", d));
+                    }
+                    open = Some(d);
+                    depth = 0;
+                }
+                Some(d @ (')' | ']')) => {
+                    let expected = if d == ')' { '(' } else { '[' };
+                    if open != Some(expected) {
+                        return Err(format!("`\\{}` without a matching `\\{}`", d, expected));
+                    }
+                    if depth != 0 {
+                        return Err("unbalanced braces in math".to_string());
+                    }
+                    open = None;
+                }
+                // Escaped characters such as `\{` or `\$` don't affect nesting.
+                _ => {}
+            },
+            '{' if open.is_some() => depth += 1,
+            '}' if open.is_some() => {
+                depth -= 1;
+                if depth < 0 {
+                    return Err("unexpected `}` in math".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match open {
+        Some(d) => Err(format!("`\\{}` is never closed", d)),
+        None => Ok(()),
+    }
+}
+
+fn parse_jsonl(
+    source: &str,
+    contents: &str,
+    parsed: &mut Vec<(String, usize, SATQuestion)>,
+    errors: &mut Vec<ImportError>,
+) {
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<SATQuestion>(line) {
+            Ok(question) => parsed.push((source.to_string(), index + 1, question)),
+            Err(e) => errors.push(ImportError {
+                source: source.to_string(),
+                line: index + 1,
+                message: format!("invalid JSON: {}", e),
+            }),
+        }
+    }
+}
+
+fn parse_csv(
+    source: &str,
+    contents: &str,
+    parsed: &mut Vec<(String, usize, SATQuestion)>,
+    errors: &mut Vec<ImportError>,
+) {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let header = match reader.headers() {
+        Ok(header) => header.clone(),
+        Err(e) => {
+            errors.push(ImportError { source: source.to_string(), line: 1, message: format!("invalid header: {}", e) });
+            return;
+        }
+    };
+    let missing: Vec<&str> = CSV_HEADER[..11].iter().copied().filter(|c| !header.iter().any(|h| h == *c)).collect();
+    if !missing.is_empty() {
+        errors.push(ImportError {
+            source: source.to_string(),
+            line: 1,
+            message: format!("missing columns: {}", missing.join(", ")),
+        });
+        return;
+    }
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line() as usize).unwrap_or_default();
+                errors.push(ImportError { source: source.to_string(), line, message: e.to_string() });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line() as usize).unwrap_or_default();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|h| h == name)
+                .and_then(|i| record.get(i))
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let optional = |name: &str| Some(column(name)).filter(|v| !v.is_empty());
+        let visuals = Visuals {
+            visual_type: optional("visual_type"),
+            svg_content: optional("svg_content"),
+        };
+
+        let question = SATQuestion {
+            id: column("id"),
+            domain: column("domain"),
+            visuals: (!visuals.is_empty()).then_some(visuals),
+            question: Question {
+                paragraph: optional("paragraph"),
+                question: column("question"),
+                choices: Choices {
+                    a: column("choice_a"),
+                    b: column("choice_b"),
+                    c: column("choice_c"),
+                    d: column("choice_d"),
+                },
+                correct_answer: column("correct_answer").to_ascii_uppercase(),
+                explanation: column("explanation"),
+            },
+            difficulty: column("difficulty"),
+        };
+        parsed.push((source.to_string(), line, question));
+    }
+}
+
+/// Reads `assessmentItem`s with a single four-option `choiceInteraction`. QTI has no field for the
+/// domain or difficulty, so they travel in the item's `label` as `domain|difficulty`.
+fn parse_qti(
+    source: &str,
+    contents: &str,
+    parsed: &mut Vec<(String, usize, SATQuestion)>,
+    errors: &mut Vec<ImportError>,
+) {
+    let doc = match roxmltree::Document::parse(contents) {
+        Ok(doc) => doc,
+        Err(e) => {
+            errors.push(ImportError {
+                source: source.to_string(),
+                line: e.pos().row as usize,
+                message: format!("invalid XML: {}", e),
+            });
+            return;
+        }
+    };
+
+    for item in doc.descendants().filter(|n| n.has_tag_name("assessmentItem")) {
+        let line = doc.text_pos_at(item.range().start).row as usize;
+        match qti_question(contents, item) {
+            Ok(question) => parsed.push((source.to_string(), line, question)),
+            Err(e) => errors.push(ImportError { source: source.to_string(), line, message: e.to_string() }),
+        }
+    }
+}
+
+fn qti_question(contents: &str, item: roxmltree::Node) -> Result<SATQuestion> {
+    let id = item.attribute("identifier").unwrap_or_default().to_string();
+    let (domain, difficulty) = item
+        .attribute("label")
+        .map(|label| label.split_once('|').unwrap_or((label, "")))
+        .unwrap_or_default();
+
+    let correct_id = child(item, "correctResponse")
+        .and_then(|r| child(r, "value"))
+        .and_then(|v| v.text())
+        .map(str::trim)
+        .ok_or_else(|| anyhow!("{}: no correctResponse value", id))?;
+    let body = child(item, "itemBody").ok_or_else(|| anyhow!("{}: no itemBody", id))?;
+    let interaction = child(body, "choiceInteraction").ok_or_else(|| anyhow!("{}: no choiceInteraction", id))?;
+
+    let choices: Vec<roxmltree::Node> = interaction.children().filter(|n| n.has_tag_name("simpleChoice")).collect();
+    if choices.len() != LETTERS.len() {
+        bail!("{}: expected 4 simpleChoice elements, found {}", id, choices.len());
+    }
+    let correct_answer = choices
+        .iter()
+        .position(|c| c.attribute("identifier") == Some(correct_id))
+        .map(|i| LETTERS[i].to_string())
+        .ok_or_else(|| anyhow!("{}: correctResponse `{}` is not one of the choices", id, correct_id))?;
+    let choice_text = |i: usize| node_text(choices[i]);
+
+    // Everything in the item body ahead of the interaction is the passage.
+    let paragraph = body
+        .children()
+        .filter(|n| n.is_element() && n.range().end <= interaction.range().start && !n.has_tag_name("svg"))
+        .map(node_text)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let svg = body.descendants().find(|n| n.has_tag_name("svg")).map(|n| contents[n.range()].to_string());
+    let explanation = child(item, "modalFeedback").map(node_text).unwrap_or_default();
+
+    Ok(SATQuestion {
+        id,
+        domain: domain.trim().to_string(),
+        visuals: svg.map(|svg| Visuals {
+            visual_type: Some("svg".to_string()),
+            svg_content: Some(svg),
+        }),
+        question: Question {
+            paragraph: (!paragraph.is_empty()).then_some(paragraph),
+            question: child(interaction, "prompt").map(node_text).unwrap_or_default(),
+            choices: Choices {
+                a: choice_text(0),
+                b: choice_text(1),
+                c: choice_text(2),
+                d: choice_text(3),
+            },
+            correct_answer,
+            explanation,
+        },
+        difficulty: difficulty.trim().to_string(),
+    })
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.descendants().find(|n| n.has_tag_name(name))
+}
+
+/// Text content of a node with whitespace collapsed.
+fn node_text(node: roxmltree::Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn qti_item(q: &SATQuestion) -> String {
+    let mut body = String::new();
+    if let Some(paragraph) = &q.question.paragraph {
+        for paragraph in paragraph.split("\n\n") {
+            body.push_str(&format!("    <p>{}</p>\n", xml_escape(paragraph)));
+        }
+    }
+    if let Some(svg) = q.visuals.as_ref().and_then(|v| v.svg_content.as_deref()) {
+        match embeddable_svg(svg) {
+            Some(svg) => body.push_str(&format!("    {}\n", svg)),
+            None => tracing::warn!("{}: svg_content isn't well-formed XML, leaving it out of the QTI item", q.id),
+        }
+    }
+
+    let choices: String = LETTERS
+        .iter()
+        .zip([&q.question.choices.a, &q.question.choices.b, &q.question.choices.c, &q.question.choices.d])
+        .map(|(letter, text)| {
+            format!("      <simpleChoice identifier=\"{}\">{}</simpleChoice>\n", letter, xml_escape(text))
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<assessmentItem xmlns="http://www.imsglobal.org/xsd/imsqti_v2p1" identifier="{id}" title="{id}" label="{label}" adaptive="false" timeDependent="false">
+  <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="identifier">
+    <correctResponse>
+      <value>{correct}</value>
+    </correctResponse>
+  </responseDeclaration>
+  <outcomeDeclaration identifier="SCORE" cardinality="single" baseType="float"/>
+  <itemBody>
+{body}    <choiceInteraction responseIdentifier="RESPONSE" shuffle="false" maxChoices="1">
+      <prompt>{prompt}</prompt>
+{choices}    </choiceInteraction>
+  </itemBody>
+  <responseProcessing template="http://www.imsglobal.org/question/qti_v2p1/rptemplates/match_correct"/>
+  <modalFeedback outcomeIdentifier="SCORE" identifier="EXPLANATION" showHide="show">{explanation}</modalFeedback>
+</assessmentItem>
+"#,
+        id = xml_escape(&q.id),
+        label = xml_escape(&format!("{}|{}", q.domain, q.difficulty)),
+        correct = xml_escape(&q.question.correct_answer),
+        body = body,
+        prompt = xml_escape(&q.question.question),
+        choices = choices,
+        explanation = xml_escape(&q.question.explanation),
+    )
+}
+
+/// The SVG without its XML declaration or doctype, which can't appear inside another document,
+/// or `None` when what's left isn't a well-formed `<svg>` element.
+fn embeddable_svg(svg: &str) -> Option<&str> {
+    let mut rest = svg.trim();
+    while rest.starts_with("<?") || rest.starts_with("<!") {
+        let end = if rest.starts_with("<!--") { rest.find("-->")? + 3 } else { rest.find('>')? + 1 };
+        rest = rest[end..].trim_start();
+    }
+    let doc = roxmltree::Document::parse(rest).ok()?;
+    doc.root_element().has_tag_name("svg").then_some(rest)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn sanitize_file_name(id: &str) -> String {
+    id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample(id: &str, svg: Option<&str>) -> SATQuestion {
+        SATQuestion {
+            id: id.to_string(),
+            domain: "Algebra".to_string(),
+            visuals: svg.map(|svg| Visuals {
+                visual_type: Some("svg".to_string()),
+                svg_content: Some(svg.to_string()),
+            }),
+            question: Question {
+                paragraph: Some("A line passes through \\((0, 2)\\) & \"rises\" <steeply>.".to_string()),
+                question: "What is the slope, \\(m\\), of the line?".to_string(),
+                choices: Choices {
+                    a: "1".to_string(),
+                    b: "2".to_string(),
+                    c: "3".to_string(),
+                    d: "\\(\\frac{1}{2}\\)".to_string(),
+                },
+                correct_answer: "B".to_string(),
+                explanation: "Rise over run is 2.".to_string(),
+            },
+            difficulty: "Medium".to_string(),
+        }
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sat-bot-bank-io-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn round_trip(format: BankFormat, path: &Path, questions: &[SATQuestion]) -> Vec<SATQuestion> {
+        export_questions(path, format, questions).unwrap();
+        let (imported, errors) = import_questions(path, format).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        imported
+    }
+
+    fn as_json(questions: &[SATQuestion]) -> serde_json::Value {
+        serde_json::to_value(questions).unwrap()
+    }
+
+    #[test]
+    fn jsonl_round_trip() {
+        let dir = scratch("jsonl");
+        let questions = [sample("q1", None), sample("q2", Some("<svg xmlns=\"http://www.w3.org/2000/svg\"/>"))];
+        let imported = round_trip(BankFormat::Jsonl, &dir.join("bank.jsonl"), &questions);
+        assert_eq!(as_json(&imported), as_json(&questions));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let dir = scratch("csv");
+        let questions = [sample("q1", None), sample("q2", Some("<svg xmlns=\"http://www.w3.org/2000/svg\"/>"))];
+        let imported = round_trip(BankFormat::Csv, &dir.join("bank.csv"), &questions);
+        assert_eq!(as_json(&imported), as_json(&questions));
+    }
+
+    #[test]
+    fn qti_round_trip() {
+        let dir = scratch("qti");
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><line x1="0" y1="0" x2="10" y2="10"/></svg>"#;
+        let questions = [sample("q1", None), sample("q2", Some(svg))];
+        let imported = round_trip(BankFormat::Qti, &dir, &questions);
+        assert_eq!(as_json(&imported), as_json(&questions));
+    }
+
+    #[test]
+    fn qti_export_strips_the_svg_prolog() {
+        let dir = scratch("qti-prolog");
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><circle r="4"/></svg>"#;
+        let prolog = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!-- drawn by hand -->\n<!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\">\n{}",
+            svg
+        );
+        let imported = round_trip(BankFormat::Qti, &dir, &[sample("q1", Some(&prolog))]);
+        let visuals = imported[0].visuals.as_ref().unwrap();
+        assert_eq!(visuals.svg_content.as_deref(), Some(svg));
+    }
+
+    #[test]
+    fn qti_export_drops_malformed_svg() {
+        let dir = scratch("qti-malformed");
+        let imported = round_trip(BankFormat::Qti, &dir, &[sample("q1", Some("<svg><g></svg>"))]);
+        assert!(imported[0].visuals.is_none());
+    }
+
+    #[test]
+    fn import_rejects_duplicate_ids() {
+        let dir = scratch("duplicates");
+        let path = dir.join("bank.jsonl");
+        export_questions(&path, BankFormat::Jsonl, &[sample("q1", None), sample("q2", None), sample("q1", None)]).unwrap();
+        let (imported, errors) = import_questions(&path, BankFormat::Jsonl).unwrap();
+        assert_eq!(imported.iter().map(|q| q.id.as_str()).collect::<Vec<_>>(), ["q1", "q2"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        assert!(errors[0].message.contains("duplicate id"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn check_latex_accepts_balanced_math() {
+        assert_eq!(check_latex("no math"), Ok(()));
+        assert_eq!(check_latex("\\(x^{2}\\) and \\[\\frac{a}{b}\\]"), Ok(()));
+        assert_eq!(check_latex("costs \\$5 \\(\\{1, 2\\}\\)"), Ok(()));
+    }
+
+    #[test]
+    fn check_latex_rejects_unpaired_delimiters() {
+        assert!(check_latex("\\(x").is_err());
+        assert!(check_latex("x\\)").is_err());
+        assert!(check_latex("\\(x\\]").is_err());
+        assert!(check_latex("\\(a \\(b\\)\\)").is_err());
+    }
+
+    #[test]
+    fn check_latex_rejects_unbalanced_braces() {
+        assert!(check_latex("\\(\\frac{1}{2\\)").is_err());
+        assert!(check_latex("\\(x}\\)").is_err());
+    }
+}
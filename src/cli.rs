@@ -0,0 +1,153 @@
+use anyhow::{anyhow, bail, Result};
+use std::path::Path;
+use crate::{
+    analytics::{export_attempts, export_item_stats, filter_attempts, item_stats, ExportFormat, ExportQuery},
+    bank::{load_full_bank, load_question_bank},
+    bank_io::{export_questions, import_questions, BankFormat},
+    lint::{format_report, lint_bank, Severity},
+    state::AppState,
+    store::CustomQuestion,
+    utils::now_unix,
+};
+
+const USAGE: &str = "usage:
+  slack-sat-bot bank import <file|dir> [--format csv|jsonl|qti] [--dry-run]
+  slack-sat-bot bank export <file|dir> [--format csv|jsonl|qti]
+  slack-sat-bot bank lint
+  slack-sat-bot analytics attempts|items [--out <file>] [--format csv|json] [--channel <id>] [--user <id>]
+                [--domain <name>] [--from YYYY-MM-DD] [--to YYYY-MM-DD]";
+
+/// Runs a command-line subcommand. Called from `main` when the binary gets arguments.
+pub async fn run(state: &AppState, args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["bank", "import", path, rest @ ..] => bank_import(state, Path::new(path), rest).await,
+        ["bank", "export", path, rest @ ..] => bank_export(state, Path::new(path), rest).await,
+        ["bank", "lint"] => bank_lint(state).await,
+        ["analytics", report @ ("attempts" | "items"), rest @ ..] => analytics_export(state, report, rest).await,
+        _ => bail!("{}", USAGE),
+    }
+}
+
+async fn bank_import(state: &AppState, path: &Path, flags: &[&str]) -> Result<()> {
+    let format = resolve_format(path, flags)?;
+    let (questions, errors) = import_questions(path, format)?;
+
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+    if !errors.is_empty() {
+        bail!("{} problem(s) found; nothing was imported", errors.len());
+    }
+    if flags.contains(&"--dry-run") {
+        println!("{} question(s) are valid", questions.len());
+        return Ok(());
+    }
+
+    let _lock = state.store.lock()?;
+    let now = now_unix();
+    let count = questions.len();
+    let replaced = state
+        .store
+        .update(|data| {
+            let mut replaced = 0;
+            for question in questions {
+                let before = data.custom_questions.len();
+                data.custom_questions.retain(|c| c.question.id != question.id);
+                replaced += before - data.custom_questions.len();
+                data.custom_questions.push(CustomQuestion {
+                    question,
+                    author: "import".to_string(),
+                    status: "approved".to_string(),
+                    created_at: now,
+                    reviewed_by: None,
+                });
+            }
+            replaced
+        })
+        .await?;
+    println!(
+        "Imported {} question(s) from {}, replacing {} with the same id",
+        count,
+        path.display(),
+        replaced
+    );
+    Ok(())
+}
+
+async fn bank_export(state: &AppState, path: &Path, flags: &[&str]) -> Result<()> {
+    let format = resolve_format(path, flags)?;
+    let bank = load_question_bank(state).await?;
+    export_questions(path, format, &bank)?;
+    println!("Exported {} question(s) to {}", bank.len(), path.display());
+    Ok(())
+}
+
+/// Lints the bank as loaded before any lint exclusion, and fails when a rule reports an error.
+async fn bank_lint(state: &AppState) -> Result<()> {
+    let bank = load_full_bank(state).await?;
+    let findings = lint_bank(&bank);
+    print!("{}", format_report(&findings, bank.len()));
+    let errors = findings.iter().filter(|f| f.severity == Severity::Error).count();
+    if errors > 0 {
+        bail!("{} error(s) found", errors);
+    }
+    Ok(())
+}
+
+/// Writes attempts or item statistics to `--out`, or stdout. The other flags filter like the
+/// `/admin/export/*` query parameters of the same names.
+async fn analytics_export(state: &AppState, report: &str, flags: &[&str]) -> Result<()> {
+    let mut out = None;
+    let mut pairs = Vec::new();
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        let name = flag.strip_prefix("--").ok_or_else(|| anyhow!("unexpected argument `{}`\n{}", flag, USAGE))?;
+        let value = flags.next().ok_or_else(|| anyhow!("--{} needs a value", name))?;
+        match name {
+            "out" => out = Some(Path::new(value)),
+            _ => pairs.push((name, *value)),
+        }
+    }
+    // Without --format, go by the output file's extension.
+    let inferred = out.and_then(|path| path.extension()).and_then(|e| e.to_str()).and_then(ExportFormat::parse);
+    let mut query = ExportQuery::parse(pairs.iter().copied())?;
+    if let (Some(format), false) = (inferred, pairs.iter().any(|(name, _)| *name == "format")) {
+        query.format = format;
+    }
+
+    let (body, count) = state
+        .store
+        .read(|data| {
+            let attempts = filter_attempts(data, &query);
+            match report {
+                "items" => {
+                    let items = item_stats(&attempts);
+                    export_item_stats(&items, query.format).map(|body| (body, items.len()))
+                }
+                _ => export_attempts(&attempts, query.format).map(|body| (body, attempts.len())),
+            }
+        })
+        .await?;
+    match out {
+        Some(path) => {
+            std::fs::write(path, body)?;
+            println!("Exported {} {} to {}", count, report, path.display());
+        }
+        None => std::io::Write::write_all(&mut std::io::stdout(), &body)?,
+    }
+    Ok(())
+}
+
+fn resolve_format(path: &Path, flags: &[&str]) -> Result<BankFormat> {
+    let explicit = flags
+        .iter()
+        .position(|f| *f == "--format")
+        .and_then(|i| flags.get(i + 1))
+        .map(|name| BankFormat::parse(name).ok_or_else(|| anyhow!("unknown format `{}`", name)))
+        .transpose()?;
+    match explicit.or_else(|| BankFormat::from_path(path)) {
+        Some(format) => Ok(format),
+        None => bail!("can't tell the format of {}; pass --format csv|jsonl|qti", path.display()),
+    }
+}
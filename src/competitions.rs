@@ -0,0 +1,561 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use crate::{
+    errors::report_failure,
+    models::*,
+    slack::{post_message, respond, section_block, slack_api, slack_api_all},
+    state::AppState,
+    hints::hint_multiplier,
+    rating::rated_attempts,
+    store::{Attempt, Competition, StoreData, Team},
+    utils::{generate_id, now_unix, parse_date},
+};
+
+pub const COMPETITION_CALLBACK_ID: &str = "competition";
+
+const WEEK: u64 = 7 * 24 * 60 * 60;
+const DAY: u64 = 24 * 60 * 60;
+/// Team slots in the setup form; empty ones are ignored.
+const TEAM_SLOTS: usize = 4;
+
+/// Carried through the modal so the submission knows where it was set up.
+#[derive(Debug, Serialize, Deserialize)]
+struct CompetitionMetadata {
+    channel_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamStanding {
+    pub name: String,
+    pub points: f64,
+    pub members: usize,
+}
+
+impl TeamStanding {
+    /// Points per member, so a big cohort doesn't win on headcount alone.
+    pub fn score(&self) -> f64 {
+        self.points / self.members.max(1) as f64
+    }
+}
+
+/// Harder questions are worth more, and hints take some of it away.
+fn points_for(attempt: &Attempt) -> f64 {
+    let base = match attempt.difficulty.to_ascii_lowercase().as_str() {
+        "hard" => 3.0,
+        "medium" => 2.0,
+        _ => 1.0,
+    };
+    base * hint_multiplier(attempt.hints)
+}
+
+/// Which team an answer counts for: a listed user's own team, otherwise the team whose channel
+/// it was given in.
+fn team_for<'a>(competition: &'a Competition, user_id: &str, channel_id: &str) -> Option<&'a Team> {
+    competition
+        .teams
+        .iter()
+        .find(|t| t.users.iter().any(|u| u == user_id))
+        .or_else(|| competition.teams.iter().find(|t| t.channels.iter().any(|c| c == channel_id)))
+}
+
+/// Points per team from correct answers in the competition window, best normalized score first.
+/// Only first answers on a message score; see [`rated_attempts`]. `sizes` is each team's
+/// headcount; see [`team_sizes`].
+pub fn standings(data: &StoreData, competition: &Competition, sizes: &HashMap<String, usize>) -> Vec<TeamStanding> {
+    let mut points: HashMap<&str, f64> = HashMap::new();
+    for attempt in rated_attempts(&data.attempts)
+        .filter(|a| a.correct && a.timestamp >= competition.starts_at && a.timestamp < competition.ends_at)
+    {
+        if let Some(team) = team_for(competition, &attempt.user_id, &attempt.channel_id) {
+            *points.entry(team.name.as_str()).or_default() += points_for(attempt);
+        }
+    }
+
+    let mut table: Vec<TeamStanding> = competition
+        .teams
+        .iter()
+        .map(|team| TeamStanding {
+            name: team.name.clone(),
+            points: points.get(team.name.as_str()).copied().unwrap_or(0.0),
+            members: sizes.get(&team.name).copied().unwrap_or(team.users.len()),
+        })
+        .collect();
+    table.sort_by(|a, b| b.score().total_cmp(&a.score()).then(b.points.total_cmp(&a.points)));
+    table
+}
+
+/// Headcount per team: listed users plus the human members of its channels, minus anyone listed
+/// on another team. Falls back to the listed users when a channel can't be read.
+pub async fn team_sizes(state: &AppState, competition: &Competition) -> HashMap<String, usize> {
+    let token = state.config.slack.bot_token.clone();
+    let listed: BTreeSet<&str> = competition.teams.iter().flat_map(|t| t.users.iter().map(String::as_str)).collect();
+    let bots = if competition.teams.iter().any(|t| !t.channels.is_empty()) {
+        bot_ids(&token).await
+    } else {
+        BTreeSet::new()
+    };
+
+    let mut sizes = HashMap::new();
+    for team in &competition.teams {
+        let mut members: BTreeSet<String> = team.users.iter().cloned().collect();
+        for channel in &team.channels {
+            let request = json!({ "channel": channel, "limit": 1000 });
+            match slack_api_all(&token, "conversations.members", &request, "members").await {
+                Ok(response) => members.extend(
+                    response
+                        .iter()
+                        .filter_map(|m| m.as_str())
+                        .filter(|m| !bots.contains(*m))
+                        .filter(|m| !listed.contains(m) || team.users.iter().any(|u| u == m))
+                        .map(str::to_string),
+                ),
+                Err(e) => tracing::warn!("Failed to count members of {} for team {}: {}", channel, team.name, e),
+            }
+        }
+        sizes.insert(team.name.clone(), members.len());
+    }
+    sizes
+}
+
+/// The workspace's bot users, which sit in channels but don't compete. Empty when `users.list`
+/// fails, so a missing scope overcounts rather than breaking standings.
+async fn bot_ids(token: &str) -> BTreeSet<String> {
+    match slack_api_all(token, "users.list", &json!({ "limit": 1000 }), "members").await {
+        Ok(users) => users
+            .iter()
+            .filter(|u| u["is_bot"].as_bool() == Some(true) || u["id"] == "USLACKBOT")
+            .filter_map(|u| u["id"].as_str().map(str::to_string))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Failed to list bot users, counting every channel member: {}", e);
+            BTreeSet::new()
+        }
+    }
+}
+
+/// Start of the competition's last day, for `<!date>` ranges. Finishing early moves `ends_at`
+/// off midnight, so this rounds down instead of subtracting a day.
+fn last_day(competition: &Competition) -> u64 {
+    competition.ends_at.saturating_sub(1) / DAY * DAY
+}
+
+fn standings_text(competition: &Competition, table: &[TeamStanding], heading: &str) -> String {
+    let medals = ["🥇", "🥈", "🥉"];
+    let rows = table
+        .iter()
+        .enumerate()
+        .map(|(i, team)| {
+            format!(
+                "{} *{}* — {:.1} pts/member ({} pts, {} member{})",
+                medals.get(i).copied().unwrap_or("▫️"),
+                team.name,
+                team.score(),
+                team.points,
+                team.members,
+                if team.members == 1 { "" } else { "s" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "{} *{}*\n<!date^{}^{{date_short}}|start> – <!date^{}^{{date_short}}|end> · Easy 1 pt, Medium 2, Hard 3, a quarter less per hint, divided by team size.\n{}",
+        heading,
+        competition.name,
+        competition.starts_at,
+        last_day(competition),
+        rows
+    )
+}
+
+/// Every channel that hears about the competition: the teams' channels and the one it was set up in.
+fn participating_channels(competition: &Competition) -> BTreeSet<String> {
+    competition
+        .teams
+        .iter()
+        .flat_map(|t| t.channels.iter().cloned())
+        .chain(std::iter::once(competition.channel_id.clone()))
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+async fn announce(state: &AppState, competition: &Competition, text: &str) {
+    let token = state.config.slack.bot_token.clone();
+    for channel in participating_channels(competition) {
+        if let Err(e) = post_message(&token, &channel, vec![section_block(text)]).await {
+            tracing::error!("Failed to post competition update to {}: {}", channel, e);
+        }
+    }
+}
+
+/// Handles `/sat compete` (set one up), `/sat compete standings` and `/sat compete end`. Returns
+/// `None` when a modal is opening and there's nothing to reply.
+pub async fn start_competition(state: &AppState, command: &SlackSlashCommand, args: &str) -> Option<Value> {
+    let ephemeral = |text: &str| Some(json!({ "response_type": "ephemeral", "text": text }));
+    let action = args.split_whitespace().next().unwrap_or("").to_lowercase();
+    let is_admin = state.config.is_admin(&command.user_id);
+
+    match action.as_str() {
+        "standings" => {
+            let response_url = command.response_url.clone();
+            state.spawn(move |state| async move {
+                if let Err(e) = send_standings(&state, &response_url).await {
+                    tracing::error!("Failed to send competition standings: {}", e);
+                }
+            });
+            ephemeral("🏁 Tallying the standings...")
+        }
+        "end" if is_admin => {
+            let active = state.store.read(|data| active_competition(data).map(|c| c.id.clone())).await;
+            let Some(id) = active else {
+                return ephemeral("There's no competition running.");
+            };
+            state.spawn(move |state| async move {
+                if let Err(e) = finish_competition(&state, &id, now_unix()).await {
+                    tracing::error!("Failed to end competition {}: {}", id, e);
+                }
+            });
+            ephemeral("🏁 Ending the competition and posting final results.")
+        }
+        "" | "new" if is_admin => {
+            if state.store.read(|data| active_competition(data).is_some()).await {
+                return ephemeral("A competition is already running. End it first with `/sat compete end`.");
+            }
+            let (trigger_id, channel_id) = (command.trigger_id.clone(), command.channel_id.clone());
+            let response_url = command.response_url.clone();
+            state.spawn(move |state| async move {
+                if let Err(e) = open_competition_modal(&state, &trigger_id, &channel_id).await {
+                    report_failure(&response_url, "opening the competition form", &e).await;
+                }
+            });
+            None
+        }
+        "" | "new" | "end" => ephemeral("🔒 Only admins can set up or end competitions."),
+        _ => ephemeral("Usage: `/sat compete` to set one up, `/sat compete standings`, or `/sat compete end`."),
+    }
+}
+
+/// The competition that hasn't finished yet, if any. Only one runs at a time.
+fn active_competition(data: &StoreData) -> Option<&Competition> {
+    data.competitions.iter().find(|c| c.status == "active")
+}
+
+async fn send_standings(state: &AppState, response_url: &str) -> Result<()> {
+    let Some(competition) = state.store.read(|data| active_competition(data).cloned()).await else {
+        return respond(response_url, &json!({
+            "response_type": "ephemeral",
+            "replace_original": true,
+            "text": "There's no competition running.",
+        }))
+        .await;
+    };
+    let sizes = team_sizes(state, &competition).await;
+    let table = state.store.read(|data| standings(data, &competition, &sizes)).await;
+    respond(response_url, &json!({
+        "response_type": "ephemeral",
+        "replace_original": true,
+        "text": standings_text(&competition, &table, "📊 Standings so far:"),
+    }))
+    .await
+}
+
+async fn open_competition_modal(state: &AppState, trigger_id: &str, channel_id: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let metadata = serde_json::to_string(&CompetitionMetadata {
+        channel_id: channel_id.to_string(),
+    })?;
+
+    let mut blocks = vec![
+        json!({
+            "type": "input",
+            "block_id": "name",
+            "label": { "type": "plain_text", "text": "Name" },
+            "element": { "type": "plain_text_input", "action_id": "input", "placeholder": { "type": "plain_text", "text": "Spring Cup" } }
+        }),
+        json!({
+            "type": "input",
+            "block_id": "starts",
+            "label": { "type": "plain_text", "text": "Starts" },
+            "element": { "type": "datepicker", "action_id": "date" }
+        }),
+        json!({
+            "type": "input",
+            "block_id": "ends",
+            "label": { "type": "plain_text", "text": "Ends (inclusive)" },
+            "element": { "type": "datepicker", "action_id": "date" }
+        }),
+    ];
+    for slot in 1..=TEAM_SLOTS {
+        blocks.push(json!({ "type": "divider" }));
+        blocks.push(json!({
+            "type": "input",
+            "block_id": format!("team_{}_name", slot),
+            "optional": slot > 2,
+            "label": { "type": "plain_text", "text": format!("Team {} name", slot) },
+            "element": { "type": "plain_text_input", "action_id": "input" }
+        }));
+        blocks.push(json!({
+            "type": "input",
+            "block_id": format!("team_{}_channels", slot),
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Cohort channels" },
+            "element": {
+                "type": "multi_conversations_select",
+                "action_id": "select",
+                "filter": { "include": ["public", "private"], "exclude_bot_users": true }
+            }
+        }));
+        blocks.push(json!({
+            "type": "input",
+            "block_id": format!("team_{}_users", slot),
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Members" },
+            "element": { "type": "multi_users_select", "action_id": "select" }
+        }));
+    }
+
+    slack_api(&token, "views.open", &json!({
+        "trigger_id": trigger_id,
+        "view": {
+            "type": "modal",
+            "callback_id": COMPETITION_CALLBACK_ID,
+            "private_metadata": metadata,
+            "title": { "type": "plain_text", "text": "New competition" },
+            "submit": { "type": "plain_text", "text": "Start" },
+            "close": { "type": "plain_text", "text": "Cancel" },
+            "blocks": blocks,
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Validates the form and starts the competition. Runs inline because Slack expects the
+/// `response_action` in the HTTP response.
+pub async fn handle_competition_submission(state: &AppState, interaction: &SlackInteraction) -> Value {
+    let Some(view) = &interaction.view else {
+        return json!({});
+    };
+    if !state.config.is_admin(&interaction.user.id) {
+        return json!({});
+    }
+    let metadata: CompetitionMetadata = match serde_json::from_str(&view.private_metadata) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            tracing::error!("Bad competition metadata: {}", e);
+            return json!({});
+        }
+    };
+
+    let mut errors = Map::new();
+    let name = view.input_value("name").map(|n| n.trim().to_string()).unwrap_or_default();
+    let starts_at = view.input_value("starts").as_deref().and_then(parse_date);
+    let ends_at = view.input_value("ends").as_deref().and_then(parse_date).map(|d| d + DAY);
+    match (starts_at, ends_at) {
+        (Some(start), Some(end)) if end <= start => {
+            errors.insert("ends".to_string(), json!("The competition has to end on or after its start date."));
+        }
+        (Some(_), Some(end)) if end <= now_unix() => {
+            errors.insert("ends".to_string(), json!("Pick an end date in the future."));
+        }
+        _ => {}
+    }
+
+    let mut teams: Vec<Team> = Vec::new();
+    for slot in 1..=TEAM_SLOTS {
+        let team = Team {
+            name: view.input_value(&format!("team_{}_name", slot)).map(|n| n.trim().to_string()).unwrap_or_default(),
+            users: view.input_values(&format!("team_{}_users", slot)),
+            channels: view.input_values(&format!("team_{}_channels", slot)),
+        };
+        let name_block = format!("team_{}_name", slot);
+        if team.name.is_empty() {
+            if !team.users.is_empty() || !team.channels.is_empty() {
+                errors.insert(name_block, json!("Give this team a name."));
+            }
+            continue;
+        }
+        if team.users.is_empty() && team.channels.is_empty() {
+            errors.insert(name_block, json!("Add at least one channel or member."));
+        } else if teams.iter().any(|t| t.name.eq_ignore_ascii_case(&team.name)) {
+            errors.insert(name_block, json!("Team names have to be different."));
+        }
+        teams.push(team);
+    }
+    if teams.len() < 2 && !errors.contains_key("team_2_name") {
+        errors.insert("team_2_name".to_string(), json!("A competition needs at least two teams."));
+    }
+    if !errors.is_empty() {
+        return json!({ "response_action": "errors", "errors": errors });
+    }
+    let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) else {
+        return json!({});
+    };
+
+    let competition = Competition {
+        id: generate_id(),
+        name,
+        teams,
+        starts_at,
+        ends_at,
+        created_by: interaction.user.id.clone(),
+        channel_id: metadata.channel_id,
+        status: "active".to_string(),
+        weeks_posted: 0,
+    };
+    state.spawn(move |state| async move {
+        let saved = state
+            .store
+            .update(|data| {
+                if active_competition(data).is_some() {
+                    return false;
+                }
+                data.competitions.push(competition.clone());
+                true
+            })
+            .await;
+        match saved {
+            Ok(true) => {
+                let teams = competition
+                    .teams
+                    .iter()
+                    .map(|t| format!("• *{}*", t.name))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let text = format!(
+                    "🏁 *{}* is on! <!date^{}^{{date_short}}|start> – <!date^{}^{{date_short}}|end>\n{}\nEvery correct answer scores for your team: Easy 1 pt, Medium 2, Hard 3, a quarter less per hint, divided by team size. Check `/sat compete standings` any time.",
+                    competition.name,
+                    competition.starts_at,
+                    last_day(&competition),
+                    teams
+                );
+                announce(&state, &competition, &text).await;
+            }
+            Ok(false) => tracing::warn!("Another competition started first; dropping {}", competition.name),
+            Err(e) => tracing::error!("Failed to save competition {}: {}", competition.name, e),
+        }
+    });
+    json!({})
+}
+
+/// Posts weekly standings for the running competition and the final results once it ends.
+pub async fn run_competitions(state: &AppState) -> Result<()> {
+    let now = now_unix();
+    let Some(competition) = state.store.read(|data| active_competition(data).cloned()).await else {
+        return Ok(());
+    };
+    if now >= competition.ends_at {
+        return finish_competition(state, &competition.id, now).await;
+    }
+
+    let weeks = now.saturating_sub(competition.starts_at) / WEEK;
+    if now < competition.starts_at || weeks <= competition.weeks_posted {
+        return Ok(());
+    }
+    state
+        .store
+        .update(|data| {
+            if let Some(c) = data.competitions.iter_mut().find(|c| c.id == competition.id) {
+                c.weeks_posted = weeks;
+            }
+        })
+        .await?;
+
+    let sizes = team_sizes(state, &competition).await;
+    let table = state.store.read(|data| standings(data, &competition, &sizes)).await;
+    let heading = format!("📊 Week {} standings:", weeks);
+    announce(state, &competition, &standings_text(&competition, &table, &heading)).await;
+    Ok(())
+}
+
+/// Closes the competition at `now` and announces the final results.
+async fn finish_competition(state: &AppState, id: &str, now: u64) -> Result<()> {
+    let finished = state
+        .store
+        .update(|data| {
+            let competition = data.competitions.iter_mut().find(|c| c.id == id && c.status == "active")?;
+            competition.status = "finished".to_string();
+            competition.ends_at = competition.ends_at.min(now);
+            Some(competition.clone())
+        })
+        .await?;
+    let Some(competition) = finished else {
+        return Ok(());
+    };
+
+    let sizes = team_sizes(state, &competition).await;
+    let table = state.store.read(|data| standings(data, &competition, &sizes)).await;
+    let mut text = standings_text(&competition, &table, "🏆 Final results:");
+    if let Some(winner) = table.first().filter(|t| t.points > 0.0) {
+        text.push_str(&format!("\n\n🎉 Congratulations, *{}*!", winner.name));
+    }
+    announce(state, &competition, &text).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    fn competition() -> Competition {
+        let team = |name: &str, user: &str| Team {
+            name: name.to_string(),
+            users: vec![user.to_string()],
+            channels: Vec::new(),
+        };
+        Competition {
+            id: "c1".to_string(),
+            name: "Fall cup".to_string(),
+            teams: vec![team("Owls", "U1"), team("Foxes", "U2")],
+            starts_at: 0,
+            ends_at: 10 * DAY,
+            created_by: "UADMIN".to_string(),
+            channel_id: "C1".to_string(),
+            status: "active".to_string(),
+            weeks_posted: 0,
+        }
+    }
+
+    fn points(table: &[TeamStanding], name: &str) -> f64 {
+        table.iter().find(|t| t.name == name).unwrap().points
+    }
+
+    #[test]
+    fn retries_on_one_message_score_once() {
+        let mut data = StoreData::default();
+        for _ in 0..5 {
+            data.attempts.push(Attempt::test("U1", "q1").correct(true).on_message("1.1").at(60));
+        }
+        data.attempts.push(Attempt::test("U2", "q1").correct(true).on_message("1.1").at(60));
+        data.attempts.push(Attempt::test("U2", "q2").correct(true).on_message("2.2").at(60));
+
+        let table = standings(&data, &competition(), &HashMap::new());
+        assert!(approx_eq(points(&table, "Owls"), 2.0));
+        assert!(approx_eq(points(&table, "Foxes"), 4.0));
+        assert_eq!(table[0].name, "Foxes");
+    }
+
+    #[test]
+    fn correct_retry_after_a_miss_scores_nothing() {
+        let mut data = StoreData::default();
+        data.attempts.push(Attempt::test("U1", "q1").on_message("1.1").at(60));
+        data.attempts.push(Attempt::test("U1", "q1").correct(true).on_message("1.1").at(61));
+        // The same question posted again is a fresh chance.
+        data.attempts.push(Attempt::test("U1", "q1").correct(true).on_message("3.3").difficulty("Hard").at(62));
+
+        let table = standings(&data, &competition(), &HashMap::new());
+        assert!(approx_eq(points(&table, "Owls"), 3.0));
+    }
+
+    #[test]
+    fn answers_outside_the_window_and_hinted_answers() {
+        let mut data = StoreData::default();
+        data.attempts.push(Attempt::test("U1", "q1").correct(true).on_message("1.1").at(10 * DAY));
+        data.attempts.push(Attempt::test("U1", "q2").correct(true).on_message("2.2").hints(2).at(60));
+
+        let table = standings(&data, &competition(), &HashMap::new());
+        assert!(approx_eq(points(&table, "Owls"), 1.0));
+    }
+}
@@ -0,0 +1,444 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    env,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use crate::slack::QUESTION_SOURCE_URL;
+
+/// File read when `SAT_CONFIG` isn't set. It's fine for it not to exist.
+const DEFAULT_CONFIG_PATH: &str = "sat-bot.toml";
+const DIFFICULTIES: [&str; 3] = ["Easy", "Medium", "Hard"];
+const ACHIEVEMENT_KINDS: [&str; 5] = ["correct", "attempts", "streak", "all_domains", "perfect_round"];
+
+/// Settings from `sat-bot.toml` (or `SAT_CONFIG`), with environment variables layered on top.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// `SAT_BIND_ADDRESS`
+    pub bind_address: SocketAddr,
+    /// `SAT_STORE_PATH`
+    pub store_path: PathBuf,
+    pub slack: SlackConfig,
+    pub questions: QuestionConfig,
+    pub timeouts: TimeoutConfig,
+    pub moderation: ModerationConfig,
+    pub review: ReviewConfig,
+    pub digest: DigestConfig,
+    pub analytics: AnalyticsConfig,
+    /// Defaults for every channel; entries in `channels` override them per channel ID.
+    pub defaults: ChannelDefaults,
+    pub channels: HashMap<String, ChannelDefaults>,
+    pub features: Features,
+    /// Extra achievements on top of the built-in ones; an entry with a built-in's `id` replaces it.
+    pub achievements: Vec<AchievementRule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlackConfig {
+    /// `SLACK_BOT_TOKEN`
+    pub bot_token: String,
+    /// `SLACK_SIGNING_SECRET`
+    pub signing_secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuestionConfig {
+    /// URLs or local file paths, merged in order. `SAT_QUESTION_SOURCES`, comma-separated.
+    pub sources: Vec<String>,
+    /// `SAT_SCORE_TABLE_PATH`
+    pub score_table_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// `SAT_HTTP_TIMEOUT_SECS`
+    pub http_secs: u64,
+    /// How long shutdown waits for in-flight background work. `SAT_DRAIN_TIMEOUT_SECS`
+    pub drain_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModerationConfig {
+    /// `SAT_MODERATOR_CHANNEL`
+    pub channel: Option<String>,
+    /// `SAT_ADMIN_USERS`, comma-separated. Only these users can moderate, approve authored questions
+    /// and run competitions; when empty, nobody can.
+    pub admins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReviewConfig {
+    /// UTC hour the daily "questions due" DM goes out. `SAT_REVIEW_REMINDER_HOUR`
+    pub reminder_hour: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// Bearer token for the `/admin/export/*` endpoints, which are off while it's unset.
+    /// `SAT_ANALYTICS_TOKEN`
+    pub api_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestConfig {
+    /// Day the weekly digests go out, e.g. "monday". `SAT_DIGEST_DAY`
+    pub weekday: String,
+    /// UTC hour on that day. `SAT_DIGEST_HOUR`
+    pub hour: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelDefaults {
+    /// Restricts `/sat` questions to one domain.
+    pub domain: Option<String>,
+    /// Restricts `/sat` questions to one difficulty.
+    pub difficulty: Option<String>,
+    /// How long a question counts as seen. `SAT_REPEAT_WINDOW_DAYS` sets the global default.
+    pub repeat_window_days: Option<u64>,
+}
+
+/// A declarative achievement: earned once `kind` reaches `count`, optionally counting only
+/// attempts that match `difficulty`, `domain` and `source`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AchievementRule {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_badge")]
+    pub badge: String,
+    /// Announcement text; `{user}` and `{name}` are filled in.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// "correct", "attempts", "streak", "all_domains" or "perfect_round".
+    pub kind: String,
+    #[serde(default = "default_count")]
+    pub count: u64,
+    #[serde(default)]
+    pub difficulty: Option<String>,
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// Attempt source: "channel", "duel" or "practice".
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+fn default_badge() -> String {
+    "🏅".to_string()
+}
+
+fn default_count() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Features {
+    pub duels: bool,
+    pub practice: bool,
+    pub adaptive: bool,
+    pub review: bool,
+    pub authoring: bool,
+    pub reports: bool,
+    pub streaks: bool,
+    pub achievements: bool,
+    pub competitions: bool,
+    pub digests: bool,
+    pub hints: bool,
+    /// Drop questions that fail lint from rotation. `SAT_LINT_EXCLUDE`
+    pub lint_exclude: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            store_path: PathBuf::from("sat-bot-data.json"),
+            slack: SlackConfig::default(),
+            questions: QuestionConfig::default(),
+            timeouts: TimeoutConfig::default(),
+            moderation: ModerationConfig::default(),
+            review: ReviewConfig::default(),
+            digest: DigestConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            defaults: ChannelDefaults {
+                repeat_window_days: Some(30),
+                ..ChannelDefaults::default()
+            },
+            channels: HashMap::new(),
+            features: Features::default(),
+            achievements: Vec::new(),
+        }
+    }
+}
+
+impl Default for QuestionConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![QUESTION_SOURCE_URL.to_string()],
+            score_table_path: None,
+        }
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self { http_secs: 10, drain_secs: 30 }
+    }
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self { reminder_hour: 15 }
+    }
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            weekday: "monday".to_string(),
+            hour: 14,
+        }
+    }
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            duels: true,
+            practice: true,
+            adaptive: true,
+            review: true,
+            authoring: true,
+            reports: true,
+            streaks: true,
+            achievements: true,
+            competitions: true,
+            digests: true,
+            hints: true,
+            lint_exclude: false,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, if any, then applies environment overrides.
+    pub fn load() -> Result<Self> {
+        let (path, required) = match env::var("SAT_CONFIG") {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+        };
+
+        let mut config = if path.exists() {
+            Self::from_file(&path)?
+        } else if required {
+            bail!("config file {} does not exist", path.display());
+        } else {
+            Self::default()
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let list = |value: String| -> Vec<String> {
+            value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+        };
+        let number = |name: &str, value: String| -> Result<u64> {
+            value.trim().parse().with_context(|| format!("{} must be a whole number, got `{}`", name, value))
+        };
+
+        if let Some(value) = var("SAT_BIND_ADDRESS") {
+            self.bind_address = value
+                .parse()
+                .with_context(|| format!("SAT_BIND_ADDRESS must look like 127.0.0.1:3000, got `{}`", value))?;
+        }
+        if let Some(value) = var("SAT_STORE_PATH") {
+            self.store_path = PathBuf::from(value);
+        }
+        if let Some(value) = var("SLACK_BOT_TOKEN") {
+            self.slack.bot_token = value;
+        }
+        if let Some(value) = var("SLACK_SIGNING_SECRET") {
+            self.slack.signing_secret = Some(value);
+        }
+        if let Some(value) = var("SAT_QUESTION_SOURCES") {
+            self.questions.sources = list(value);
+        }
+        if let Some(value) = var("SAT_SCORE_TABLE_PATH") {
+            self.questions.score_table_path = Some(PathBuf::from(value));
+        }
+        if let Some(value) = var("SAT_HTTP_TIMEOUT_SECS") {
+            self.timeouts.http_secs = number("SAT_HTTP_TIMEOUT_SECS", value)?;
+        }
+        if let Some(value) = var("SAT_DRAIN_TIMEOUT_SECS") {
+            self.timeouts.drain_secs = number("SAT_DRAIN_TIMEOUT_SECS", value)?;
+        }
+        if let Some(value) = var("SAT_MODERATOR_CHANNEL") {
+            self.moderation.channel = Some(value);
+        }
+        if let Some(value) = var("SAT_ADMIN_USERS") {
+            self.moderation.admins = list(value);
+        }
+        if let Some(value) = var("SAT_REVIEW_REMINDER_HOUR") {
+            self.review.reminder_hour = number("SAT_REVIEW_REMINDER_HOUR", value)?;
+        }
+        if let Some(value) = var("SAT_DIGEST_DAY") {
+            self.digest.weekday = value.trim().to_string();
+        }
+        if let Some(value) = var("SAT_DIGEST_HOUR") {
+            self.digest.hour = number("SAT_DIGEST_HOUR", value)?;
+        }
+        if let Some(value) = var("SAT_ANALYTICS_TOKEN") {
+            self.analytics.api_token = Some(value);
+        }
+        if let Some(value) = var("SAT_REPEAT_WINDOW_DAYS") {
+            self.defaults.repeat_window_days = Some(number("SAT_REPEAT_WINDOW_DAYS", value)?);
+        }
+        if let Some(value) = var("SAT_LINT_EXCLUDE") {
+            self.features.lint_exclude = matches!(value.trim(), "1" | "true" | "yes");
+        }
+        Ok(())
+    }
+
+    /// Checks everything up front and reports every problem at once. The Slack token is only
+    /// required when `serving`; CLI commands don't talk to Slack.
+    pub fn validate(&self, serving: bool) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if serving && self.slack.bot_token.trim().is_empty() {
+            problems.push("slack.bot_token is not set (or set SLACK_BOT_TOKEN)".to_string());
+        }
+        if serving && self.slack.signing_secret.as_deref().is_none_or(|s| s.trim().is_empty()) {
+            problems.push("slack.signing_secret is not set (or set SLACK_SIGNING_SECRET); it's how requests are checked to come from Slack".to_string());
+        }
+        if self.questions.sources.is_empty() {
+            problems.push("questions.sources needs at least one URL or file".to_string());
+        }
+        for source in &self.questions.sources {
+            if !is_url(source) && !Path::new(source).is_file() {
+                problems.push(format!("questions.sources: `{}` is neither an http(s) URL nor a readable file", source));
+            }
+        }
+        if let Some(path) = &self.questions.score_table_path {
+            if !path.is_file() {
+                problems.push(format!("questions.score_table_path: {} does not exist", path.display()));
+            }
+        }
+        if self.timeouts.http_secs == 0 {
+            problems.push("timeouts.http_secs must be greater than zero".to_string());
+        }
+        if self.review.reminder_hour > 23 {
+            problems.push(format!("review.reminder_hour must be 0-23, got {}", self.review.reminder_hour));
+        }
+        if self.digest.hour > 23 {
+            problems.push(format!("digest.hour must be 0-23, got {}", self.digest.hour));
+        }
+        if self.digest_weekday().is_none() {
+            problems.push(format!("digest.weekday must be a day of the week, got `{}`", self.digest.weekday));
+        }
+        if self.analytics.api_token.as_deref().is_some_and(|t| t.trim().len() < 16) {
+            problems.push("analytics.api_token must be at least 16 characters".to_string());
+        }
+        if self.moderation.channel.as_deref().is_some_and(|c| c.trim().is_empty()) {
+            problems.push("moderation.channel is empty".to_string());
+        }
+
+        let channels = std::iter::once(("defaults".to_string(), &self.defaults))
+            .chain(self.channels.iter().map(|(id, c)| (format!("channels.{}", id), c)));
+        for (name, defaults) in channels {
+            if let Some(difficulty) = &defaults.difficulty {
+                if !DIFFICULTIES.iter().any(|d| d.eq_ignore_ascii_case(difficulty)) {
+                    problems.push(format!("{}.difficulty must be Easy, Medium or Hard, got `{}`", name, difficulty));
+                }
+            }
+        }
+
+        let mut achievement_ids = std::collections::HashSet::new();
+        for rule in &self.achievements {
+            if !achievement_ids.insert(rule.id.as_str()) {
+                problems.push(format!("achievements: `{}` is defined twice", rule.id));
+            }
+            if !ACHIEVEMENT_KINDS.contains(&rule.kind.as_str()) {
+                problems.push(format!(
+                    "achievements.{}.kind must be one of {}, got `{}`",
+                    rule.id,
+                    ACHIEVEMENT_KINDS.join(", "),
+                    rule.kind
+                ));
+            }
+            if rule.count == 0 {
+                problems.push(format!("achievements.{}.count must be greater than zero", rule.id));
+            }
+            if let Some(difficulty) = &rule.difficulty {
+                if !DIFFICULTIES.iter().any(|d| d.eq_ignore_ascii_case(difficulty)) {
+                    problems.push(format!("achievements.{}.difficulty must be Easy, Medium or Hard, got `{}`", rule.id, difficulty));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        bail!("invalid configuration:\n  - {}", problems.join("\n  - "))
+    }
+
+    /// The channel's own settings with the global defaults filled in.
+    pub fn channel_defaults(&self, channel_id: Option<&str>) -> ChannelDefaults {
+        let own = channel_id.and_then(|c| self.channels.get(c)).cloned().unwrap_or_default();
+        ChannelDefaults {
+            domain: own.domain.or_else(|| self.defaults.domain.clone()),
+            difficulty: own.difficulty.or_else(|| self.defaults.difficulty.clone()),
+            repeat_window_days: own.repeat_window_days.or(self.defaults.repeat_window_days),
+        }
+    }
+
+    /// How far back a question counts as already seen.
+    pub fn repeat_window_secs(&self, channel_id: Option<&str>) -> u64 {
+        self.channel_defaults(channel_id).repeat_window_days.unwrap_or(30) * 24 * 60 * 60
+    }
+
+    pub fn http_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeouts.http_secs)
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.timeouts.drain_secs)
+    }
+
+    /// Whether the user may run admin-only actions.
+    pub fn is_admin(&self, user_id: &str) -> bool {
+        self.moderation.admins.iter().any(|admin| admin == user_id)
+    }
+
+    /// The digest day, 0 for Monday through 6 for Sunday. Accepts full names and three-letter
+    /// abbreviations.
+    pub fn digest_weekday(&self) -> Option<u64> {
+        const DAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+        let day = self.digest.weekday.trim().to_lowercase();
+        DAYS.iter()
+            .position(|d| day.len() >= 3 && d.starts_with(&day))
+            .map(|i| i as u64)
+    }
+}
+
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
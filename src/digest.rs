@@ -0,0 +1,370 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use crate::{
+    bank::load_question_bank,
+    models::{SATQuestion, SlackSlashCommand},
+    slack::{post_message, section_block},
+    state::AppState,
+    stats::summarize_user,
+    store::{Attempt, StoreData},
+    utils::{format_text_for_slack, now_unix},
+};
+
+const DAY: u64 = 24 * 60 * 60;
+const WEEK: u64 = 7 * DAY;
+/// A question needs this many answers before it can be the week's hardest.
+const MIN_HARDEST_ATTEMPTS: usize = 2;
+/// Answers needed in each week before a change in accuracy counts as improvement.
+const MIN_IMPROVEMENT_ATTEMPTS: usize = 3;
+const QUESTION_PREVIEW_CHARS: usize = 120;
+
+/// Weeks since the epoch, starting on Mondays.
+pub fn week_index(now: u64) -> u64 {
+    (now / DAY + 3) / 7
+}
+
+/// 0 for Monday through 6 for Sunday.
+fn weekday(now: u64) -> u64 {
+    (now / DAY + 3) % 7
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    pub answered: usize,
+    pub correct: usize,
+}
+
+impl Tally {
+    fn of<'a>(attempts: impl IntoIterator<Item = &'a Attempt>) -> Self {
+        attempts.into_iter().fold(Self::default(), |tally, a| Self {
+            answered: tally.answered + 1,
+            correct: tally.correct + usize::from(a.correct),
+        })
+    }
+
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.answered > 0).then(|| self.correct as f64 / self.answered as f64)
+    }
+}
+
+/// The last seven days and the seven before them.
+fn split_weeks<'a>(attempts: impl Iterator<Item = &'a Attempt>, now: u64) -> (Vec<&'a Attempt>, Vec<&'a Attempt>) {
+    let week_start = now.saturating_sub(WEEK);
+    let previous_start = now.saturating_sub(2 * WEEK);
+    let (this_week, previous): (Vec<&Attempt>, Vec<&Attempt>) = attempts
+        .filter(|a| a.timestamp >= previous_start && a.timestamp < now)
+        .partition(|a| a.timestamp >= week_start);
+    (this_week, previous)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChannelDigest {
+    pub this_week: Tally,
+    pub previous: Tally,
+    /// The question with the lowest accuracy this week, and its tally.
+    pub hardest: Option<(String, Tally)>,
+    /// User id with their accuracy last week and this week.
+    pub most_improved: Option<(String, f64, f64)>,
+    /// Answers per domain this week.
+    pub domains: BTreeMap<String, usize>,
+}
+
+pub fn channel_digest(data: &StoreData, channel_id: &str, now: u64) -> ChannelDigest {
+    let (this_week, previous) = split_weeks(data.attempts.iter().filter(|a| a.channel_id == channel_id), now);
+
+    let mut by_question: BTreeMap<&str, Vec<&Attempt>> = BTreeMap::new();
+    for attempt in &this_week {
+        by_question.entry(attempt.question_id.as_str()).or_default().push(attempt);
+    }
+    let hardest = by_question
+        .into_iter()
+        .map(|(id, attempts)| (id, Tally::of(attempts)))
+        .filter(|(_, tally)| tally.answered >= MIN_HARDEST_ATTEMPTS)
+        .min_by(|(_, a), (_, b)| {
+            let (a_acc, b_acc) = (a.accuracy().unwrap_or(1.0), b.accuracy().unwrap_or(1.0));
+            a_acc.total_cmp(&b_acc).then(b.answered.cmp(&a.answered))
+        })
+        .map(|(id, tally)| (id.to_string(), tally));
+
+    let users: BTreeSet<&str> = this_week.iter().map(|a| a.user_id.as_str()).collect();
+    let most_improved = users
+        .into_iter()
+        .filter_map(|user| {
+            let after = Tally::of(this_week.iter().copied().filter(|a| a.user_id == user));
+            let before = Tally::of(previous.iter().copied().filter(|a| a.user_id == user));
+            if after.answered < MIN_IMPROVEMENT_ATTEMPTS || before.answered < MIN_IMPROVEMENT_ATTEMPTS {
+                return None;
+            }
+            Some((user.to_string(), before.accuracy()?, after.accuracy()?))
+        })
+        .filter(|(_, before, after)| after > before)
+        .max_by(|(_, a_before, a_after), (_, b_before, b_after)| (a_after - a_before).total_cmp(&(b_after - b_before)));
+
+    let mut domains = BTreeMap::new();
+    for attempt in this_week.iter().filter(|a| !a.domain.is_empty()) {
+        *domains.entry(attempt.domain.clone()).or_default() += 1;
+    }
+
+    ChannelDigest {
+        this_week: Tally::of(this_week),
+        previous: Tally::of(previous),
+        hardest,
+        most_improved,
+        domains,
+    }
+}
+
+/// Channels with answers in the last week. DMs and practice sessions don't get a digest.
+pub fn active_channels(data: &StoreData, now: u64) -> BTreeSet<String> {
+    let week_start = now.saturating_sub(WEEK);
+    data.attempts
+        .iter()
+        .filter(|a| a.timestamp >= week_start && !a.channel_id.is_empty() && !a.channel_id.starts_with('D'))
+        .map(|a| a.channel_id.clone())
+        .collect()
+}
+
+/// "72% (▲ 5 pts vs last week)", or just the accuracy when there's nothing to compare.
+fn trend_text(this_week: Tally, previous: Tally) -> String {
+    let Some(accuracy) = this_week.accuracy() else {
+        return "no answers".to_string();
+    };
+    match previous.accuracy() {
+        Some(before) => {
+            let delta = ((accuracy - before) * 100.0).round();
+            let arrow = match delta {
+                d if d > 0.0 => "▲",
+                d if d < 0.0 => "▼",
+                _ => "▬",
+            };
+            format!("{:.0}% ({} {:.0} pts vs last week)", accuracy * 100.0, arrow, delta.abs())
+        }
+        None => format!("{:.0}% (no answers the week before)", accuracy * 100.0),
+    }
+}
+
+fn question_preview(question: &SATQuestion) -> String {
+    let text = format_text_for_slack(&question.question.question).replace('\n', " ");
+    if text.chars().count() > QUESTION_PREVIEW_CHARS {
+        format!("{}…", text.chars().take(QUESTION_PREVIEW_CHARS).collect::<String>().trim_end())
+    } else {
+        text
+    }
+}
+
+fn channel_digest_text(digest: &ChannelDigest, bank: &[SATQuestion]) -> String {
+    let mut lines = vec![
+        "📬 *Weekly SAT digest*".to_string(),
+        format!(
+            "*Answered:* {} (last week {})   *Accuracy:* {}",
+            digest.this_week.answered,
+            digest.previous.answered,
+            trend_text(digest.this_week, digest.previous)
+        ),
+    ];
+
+    if let Some((id, tally)) = &digest.hardest {
+        let question = bank.iter().find(|q| q.id == *id);
+        let about = question
+            .map(|q| format!("_{}_ ({} · {})", question_preview(q), q.domain, q.difficulty))
+            .unwrap_or_else(|| format!("`{}`", id));
+        lines.push(format!(
+            "🧗 *Hardest question:* {} — {}/{} correct",
+            about, tally.correct, tally.answered
+        ));
+    }
+    if let Some((user, before, after)) = &digest.most_improved {
+        lines.push(format!(
+            "🚀 *Most improved:* <@{}> — {:.0}% → {:.0}%",
+            user,
+            before * 100.0,
+            after * 100.0
+        ));
+    }
+
+    let all_domains: BTreeSet<&str> = bank.iter().map(|q| q.domain.as_str()).collect();
+    let covered = digest
+        .domains
+        .iter()
+        .map(|(domain, count)| format!("{} ({})", domain, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let missing: Vec<&str> = all_domains
+        .iter()
+        .copied()
+        .filter(|d| !digest.domains.contains_key(*d))
+        .collect();
+    lines.push(format!(
+        "🗺️ *Domain coverage:* {} of {} — {}",
+        digest.domains.len(),
+        all_domains.len(),
+        if covered.is_empty() { "none".to_string() } else { covered }
+    ));
+    if !missing.is_empty() {
+        lines.push(format!("Not touched this week: {}", missing.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// A user's own digest: their week against the last, per-domain results and where to focus next.
+fn user_digest_text(data: &StoreData, user_id: &str, now: u64, bank_domains: &BTreeSet<String>) -> String {
+    let (this_week, previous) = split_weeks(data.attempts.iter().filter(|a| a.user_id == user_id), now);
+    let mut lines = vec![
+        "📬 *Your weekly SAT digest*".to_string(),
+        format!(
+            "*Answered:* {} (last week {})   *Accuracy:* {}",
+            this_week.len(),
+            previous.len(),
+            trend_text(Tally::of(this_week.iter().copied()), Tally::of(previous.iter().copied()))
+        ),
+    ];
+
+    let mut by_domain: BTreeMap<&str, Vec<&Attempt>> = BTreeMap::new();
+    for attempt in this_week.iter().filter(|a| !a.domain.is_empty()) {
+        by_domain.entry(attempt.domain.as_str()).or_default().push(attempt);
+    }
+    for (domain, attempts) in &by_domain {
+        let tally = Tally::of(attempts.iter().copied());
+        let before = Tally::of(previous.iter().copied().filter(|a| a.domain == *domain));
+        lines.push(format!("• *{}* — {}/{} correct, {}", domain, tally.correct, tally.answered, trend_text(tally, before)));
+    }
+
+    // Weakest domains overall first, then whatever went unpracticed this week.
+    let summary = summarize_user(data, user_id, now);
+    let mut focus: Vec<String> = summary.weakest_domains(2).into_iter().map(|d| d.domain.clone()).collect();
+    for domain in bank_domains {
+        if focus.len() >= 3 {
+            break;
+        }
+        if !by_domain.contains_key(domain.as_str()) && !focus.contains(domain) {
+            focus.push(domain.clone());
+        }
+    }
+    if this_week.is_empty() {
+        lines.push("No answers this week. Run `/sat` to get back into it!".to_string());
+    }
+    if !focus.is_empty() {
+        lines.push(format!("🎯 *Focus next:* {}", focus.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// Handles `/sat digest` (your digest now), `/sat digest on` and `/sat digest off` (the weekly DM).
+pub async fn start_digest(state: &AppState, command: &SlackSlashCommand, args: &str) -> Value {
+    let user_id = command.user_id.as_str();
+    let subscribe = match args.trim().to_lowercase().as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        "" => None,
+        _ => {
+            return json!({
+                "response_type": "ephemeral",
+                "text": "Usage: `/sat digest` to see yours now, or `/sat digest on` / `/sat digest off` for the weekly DM.",
+            })
+        }
+    };
+
+    let text = match subscribe {
+        Some(on) => {
+            let result = state
+                .store
+                .update(|data| {
+                    if on {
+                        data.digest_subscribers.entry(user_id.to_string()).or_insert(None);
+                    } else {
+                        data.digest_subscribers.remove(user_id);
+                    }
+                })
+                .await;
+            match result {
+                Err(e) => {
+                    tracing::error!("Failed to update digest subscription: {}", e);
+                    "Sorry, I couldn't save that setting.".to_string()
+                }
+                Ok(()) if on => format!(
+                    "📬 You'll get your digest by DM every {} at {:02}:00 UTC.",
+                    capitalize(&state.config.digest.weekday),
+                    state.config.digest.hour
+                ),
+                Ok(()) => "🔕 Weekly digest DMs turned off.".to_string(),
+            }
+        }
+        None => {
+            let domains = bank_domains(state).await;
+            state.store.read(|data| user_digest_text(data, user_id, now_unix(), &domains)).await
+        }
+    };
+    json!({ "response_type": "ephemeral", "text": text })
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.trim().chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect())
+        .unwrap_or_default()
+}
+
+async fn bank_domains(state: &AppState) -> BTreeSet<String> {
+    match load_question_bank(state).await {
+        Ok(bank) => bank.into_iter().map(|q| q.domain).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to load the bank for digest domains: {}", e);
+            BTreeSet::new()
+        }
+    }
+}
+
+/// Posts channel digests and sends subscribers theirs once the configured day and hour arrive.
+pub async fn send_digests(state: &AppState) -> Result<()> {
+    let now = now_unix();
+    let Some(day) = state.config.digest_weekday() else {
+        return Ok(());
+    };
+    if weekday(now) != day || (now % DAY) / 3600 < state.config.digest.hour {
+        return Ok(());
+    }
+    let week = week_index(now);
+
+    let (channels, subscribers) = state
+        .store
+        .update(|data| {
+            let channels = if data.last_channel_digest == Some(week) {
+                BTreeSet::new()
+            } else {
+                data.last_channel_digest = Some(week);
+                active_channels(data, now)
+            };
+            let mut subscribers = Vec::new();
+            for (user_id, last) in data.digest_subscribers.iter_mut().filter(|(_, last)| **last != Some(week)) {
+                *last = Some(week);
+                subscribers.push(user_id.clone());
+            }
+            (channels, subscribers)
+        })
+        .await?;
+    if channels.is_empty() && subscribers.is_empty() {
+        return Ok(());
+    }
+
+    let bank = load_question_bank(state).await?;
+    let domains: BTreeSet<String> = bank.iter().map(|q| q.domain.clone()).collect();
+    let token = state.config.slack.bot_token.clone();
+    for channel_id in channels {
+        let text = state
+            .store
+            .read(|data| channel_digest_text(&channel_digest(data, &channel_id, now), &bank))
+            .await;
+        if let Err(e) = post_message(&token, &channel_id, vec![section_block(&text)]).await {
+            tracing::error!("Failed to post weekly digest to {}: {}", channel_id, e);
+        }
+    }
+    for user_id in subscribers {
+        let text = state.store.read(|data| user_digest_text(data, &user_id, now, &domains)).await;
+        if let Err(e) = post_message(&token, &user_id, vec![section_block(&text)]).await {
+            tracing::error!("Failed to send weekly digest to {}: {}", user_id, e);
+        }
+    }
+    Ok(())
+}
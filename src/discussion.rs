@@ -0,0 +1,168 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use crate::{
+    bank::load_question_bank,
+    models::SATQuestion,
+    rating::rated_attempts,
+    scheduler::{schedule_job, QUESTION_CLOSE_JOB},
+    settings::channel_settings,
+    slack::{
+        create_compact_question_blocks, create_question_blocks, create_question_content_blocks, is_membership_error,
+        post_in_thread, respond, section_block, update_message,
+    },
+    state::AppState,
+    store::ScheduledJob,
+    utils::{format_text_for_slack, generate_id, now_unix},
+};
+
+/// How long a channel question stays open for discussion when the channel has no answer deadline.
+const DEFAULT_DISCUSSION_MINUTES: u64 = 60;
+
+/// Posts a question to a channel with its passage in a thread reply, and schedules the
+/// explanation for when answering closes. Run inside a thread (`thread_ts`), everything goes
+/// into that thread instead.
+///
+/// The thread and the scheduled explanation both need the message `ts`, so this posts with
+/// `chat.postMessage` first. When the bot isn't in the channel it falls back to an `in_channel`
+/// reply through `response_url`, passage included; such posts have no `ts`, so they get no thread
+/// and no explanation at close. `replace_loading` decides whether that reply replaces the
+/// command's loading message. Returns the message `ts` when there is one.
+pub async fn post_channel_question(
+    state: &AppState,
+    channel_id: &str,
+    response_url: Option<&str>,
+    thread_ts: Option<&str>,
+    question: &SATQuestion,
+    replace_loading: bool,
+) -> Result<Option<String>> {
+    let token = state.config.slack.bot_token.clone();
+    let passage = question.question.paragraph.as_deref();
+    let hints = state.config.features.hints;
+    let blocks = match (thread_ts, passage) {
+        (None, Some(_)) => create_compact_question_blocks(question, hints),
+        _ => create_question_blocks(question, hints),
+    };
+
+    let ts = match post_in_thread(&token, channel_id, thread_ts, blocks).await {
+        Ok(ts) => ts,
+        Err(e) => {
+            let Some(url) = response_url.filter(|url| !url.is_empty() && is_membership_error(&e)) else {
+                return Err(e);
+            };
+            tracing::info!("Not a member of {}, replying through response_url", channel_id);
+            respond(url, &json!({
+                "response_type": "in_channel",
+                "replace_original": replace_loading,
+                "text": "SAT question",
+                "blocks": create_question_blocks(question, hints),
+            }))
+            .await?;
+            return Ok(None);
+        }
+    };
+
+    let thread = thread_ts.unwrap_or(&ts);
+    if let (None, Some(passage)) = (thread_ts, passage) {
+        let reply = section_block(&format!("📖 *Passage*\n{}", format_text_for_slack(passage)));
+        if let Err(e) = post_in_thread(&token, channel_id, Some(thread), vec![reply]).await {
+            tracing::error!("Failed to post passage for {}: {}", question.id, e);
+        }
+    }
+
+    let minutes = channel_settings(state, Some(channel_id))
+        .await
+        .answer_deadline_minutes
+        .unwrap_or(DEFAULT_DISCUSSION_MINUTES);
+    schedule_job(state, ScheduledJob {
+        id: generate_id(),
+        kind: QUESTION_CLOSE_JOB.to_string(),
+        target: question.id.clone(),
+        user_id: String::new(),
+        run_at: now_unix() + minutes * 60,
+        channel_id: Some(channel_id.to_string()),
+        message_ts: Some(ts.clone()),
+        thread_ts: Some(thread.to_string()),
+    })
+    .await?;
+
+    Ok(Some(ts))
+}
+
+/// Runs a `question_close` job: takes the answer buttons off the question, then posts how the
+/// channel did in its thread, with the answer and explanation unless the channel's reveal policy
+/// is `never`.
+pub async fn close_question(state: &AppState, job: &ScheduledJob) -> Result<()> {
+    let (Some(channel_id), Some(message_ts), Some(thread_ts)) = (&job.channel_id, &job.message_ts, &job.thread_ts)
+    else {
+        return Ok(());
+    };
+    let bank = load_question_bank(state).await?;
+    let Some(question) = bank.iter().find(|q| q.id == job.target) else {
+        return Ok(());
+    };
+
+    let token = state.config.slack.bot_token.clone();
+    // Without this the buttons keep grading, and crediting, answers given after the reveal.
+    if let Err(e) = update_message(&token, channel_id, message_ts, &closed_message(question, thread_ts == message_ts)?).await {
+        tracing::error!("Failed to close answering on {} in {}: {}", message_ts, channel_id, e);
+    }
+
+    // Each person's first answer, as for ratings, so retries don't inflate the tally.
+    let (answered, correct) = state
+        .store
+        .read(|data| {
+            let attempts: Vec<_> = rated_attempts(&data.attempts)
+                .filter(|a| a.question_id == job.target && a.channel_id == *channel_id)
+                .filter(|a| a.message_ts.as_ref() == Some(message_ts))
+                .collect();
+            (attempts.len(), attempts.iter().filter(|a| a.correct).count())
+        })
+        .await;
+
+    let tally = match answered {
+        0 => "Nobody answered this one.".to_string(),
+        n => format!("{} of {} answered correctly.", correct, n),
+    };
+    let reveal = channel_settings(state, Some(channel_id)).await.reveal_policy != "never";
+    let text = if reveal {
+        format!(
+            "🔒 *Answering is closed.* The answer is *{}*. {}\n💡 *Explanation:* {}\n\nDiscuss it here in the thread!",
+            question.question.correct_answer,
+            tally,
+            format_text_for_slack(&question.question.explanation)
+        )
+    } else {
+        format!("🔒 *Answering is closed.* {}\n\nDiscuss it here in the thread!", tally)
+    };
+    post_in_thread(&token, channel_id, Some(thread_ts), vec![section_block(&text)]).await?;
+    Ok(())
+}
+
+/// The question as posted, with its choices listed in place of the answer buttons. Top-level posts
+/// with a passage left it in the thread, so `passage_in_thread` keeps it out here too.
+fn closed_message(question: &SATQuestion, passage_in_thread: bool) -> Result<Value> {
+    let mut content = create_question_content_blocks(question);
+    if passage_in_thread {
+        content.truncate(1);
+    }
+    let choices = [
+        ("A", &question.question.choices.a),
+        ("B", &question.question.choices.b),
+        ("C", &question.question.choices.c),
+        ("D", &question.question.choices.d),
+    ]
+    .iter()
+    .map(|(letter, text)| format!("*{}.* {}", letter, format_text_for_slack(text)))
+    .collect::<Vec<_>>()
+    .join("\n");
+
+    let mut blocks = serde_json::to_value(content)?;
+    if let Some(blocks) = blocks.as_array_mut() {
+        blocks.push(serde_json::to_value(section_block(&choices))?);
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": "🔒 Answering is closed; see the thread." }]
+        }));
+    }
+    Ok(json!({ "text": "SAT question (closed)", "blocks": blocks }))
+}
@@ -0,0 +1,522 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use crate::{
+    achievements::track_attempt,
+    bank::load_question_bank,
+    errors::report_failure,
+    history::{choose_preferring_unseen, record_served, recently_seen},
+    metrics::metrics,
+    models::*,
+    rating::record_attempt,
+    settings::channel_filter,
+    slack::{
+        create_answer_buttons, create_question_content_blocks, create_report_overflow, mrkdwn_block,
+        deliver, post_message, respond, respond_ephemeral, section_block, update_message, Delivery,
+    },
+    state::AppState,
+    streaks::track_correct_answer,
+    store::Attempt,
+    utils::{generate_id, now_unix, parse_user_mention},
+};
+
+pub const DUEL_QUESTION_COUNT: usize = 5;
+/// Challenges and unfinished duels are dropped after this long.
+const DUEL_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct Duel {
+    pub id: String,
+    pub channel_id: String,
+    /// Empty when the challenge went out through the command's `response_url`.
+    pub challenge_ts: String,
+    /// The `/sat duel` command's `response_url`, for channels the bot hasn't joined.
+    pub response_url: String,
+    pub challenger: String,
+    pub opponent: String,
+    pub questions: Vec<SATQuestion>,
+    pub players: HashMap<String, DuelProgress>,
+    /// Set as soon as the opponent clicks Accept, before the questions are fetched, so a second
+    /// click can't start the duel twice.
+    pub accepted: bool,
+    pub created_at: Instant,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DuelProgress {
+    pub answered: usize,
+    pub correct: usize,
+    pub started_at: Option<Instant>,
+    pub elapsed: Option<Duration>,
+}
+
+impl Duel {
+    fn is_finished(&self) -> bool {
+        self.players.values().all(|p| p.elapsed.is_some())
+    }
+}
+
+pub async fn start_duel(state: &AppState, command: &SlackSlashCommand, args: &str) -> Value {
+    let Some(opponent) = parse_user_mention(args) else {
+        return json!({
+            "response_type": "ephemeral",
+            "text": "Usage: `/sat duel @user` — mention the person you want to challenge."
+        });
+    };
+
+    if opponent == command.user_id {
+        return json!({
+            "response_type": "ephemeral",
+            "text": "You can't duel yourself. Pick a worthy opponent!"
+        });
+    }
+
+    let duel = Duel {
+        id: generate_id(),
+        channel_id: command.channel_id.clone(),
+        challenge_ts: String::new(),
+        response_url: command.response_url.clone(),
+        challenger: command.user_id.clone(),
+        opponent: opponent.clone(),
+        questions: Vec::new(),
+        players: HashMap::new(),
+        accepted: false,
+        created_at: Instant::now(),
+    };
+    let duel_id = duel.id.clone();
+    state.duels.lock().await.insert(duel_id.clone(), duel);
+
+    let challenger = command.user_id.clone();
+    let channel_id = command.channel_id.clone();
+    let response_url = command.response_url.clone();
+    state.spawn(move |state| async move {
+        let token = state.config.slack.bot_token.clone();
+        let message = json!({
+            "channel": channel_id,
+            "text": format!("<@{}> challenged <@{}> to an SAT duel!", challenger, opponent),
+            "blocks": challenge_blocks(&duel_id, &challenger, &opponent),
+        });
+
+        match deliver(&token, &channel_id, Some(&response_url), &message, Delivery::Message).await {
+            Ok(ts) => {
+                if let Some(duel) = state.duels.lock().await.get_mut(&duel_id) {
+                    duel.challenge_ts = ts.unwrap_or_default();
+                }
+            }
+            Err(e) => {
+                state.duels.lock().await.remove(&duel_id);
+                report_failure(&response_url, "posting the duel challenge", &e).await;
+            }
+        }
+    });
+
+    json!({
+        "response_type": "ephemeral",
+        "text": "Challenge sent! The duel starts once your opponent accepts."
+    })
+}
+
+pub async fn handle_duel_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    let value = action.value.clone().unwrap_or_default();
+
+    match action.action_id.as_str() {
+        "duel_accept" => accept_duel(state, interaction, &value).await,
+        "duel_decline" => decline_duel(state, interaction, &value).await,
+        id if id.starts_with("duel_answer_") => answer_duel(state, interaction, &value).await,
+        other => {
+            tracing::warn!("Unknown duel action: {}", other);
+            Ok(())
+        }
+    }
+}
+
+async fn accept_duel(state: &AppState, interaction: &SlackInteraction, duel_id: &str) -> Result<()> {
+    let (channel_id, challenge_ts, challenger, opponent) = {
+        let mut duels = state.duels.lock().await;
+        let Some(duel) = duels.get_mut(duel_id) else {
+            drop(duels);
+            return respond_ephemeral(&interaction.response_url, "This duel has expired.").await;
+        };
+        if duel.opponent != interaction.user.id {
+            drop(duels);
+            return respond_ephemeral(&interaction.response_url, "This challenge isn't for you.").await;
+        }
+        if duel.accepted {
+            return Ok(());
+        }
+        duel.accepted = true;
+        (duel.channel_id.clone(), duel.challenge_ts.clone(), duel.challenger.clone(), duel.opponent.clone())
+    };
+
+    let started = start_accepted_duel(state, interaction, duel_id, &channel_id, &challenge_ts, &challenger, &opponent).await;
+    if started.is_err() {
+        // Let the opponent try again.
+        if let Some(duel) = state.duels.lock().await.get_mut(duel_id) {
+            duel.accepted = false;
+        }
+    }
+    started
+}
+
+async fn start_accepted_duel(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    duel_id: &str,
+    channel_id: &str,
+    challenge_ts: &str,
+    challenger: &str,
+    opponent: &str,
+) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+
+    // A retry after a failed start reuses the questions already picked and recorded as served.
+    let picked = state.duels.lock().await.get(duel_id).map(|d| d.questions.clone()).unwrap_or_default();
+    let questions = if picked.is_empty() {
+        pick_duel_questions(state, channel_id, challenger, opponent).await?
+    } else {
+        picked
+    };
+    let Some(first) = questions.first().cloned() else {
+        return Err(anyhow::anyhow!("No questions available in the response"));
+    };
+    let total = questions.len();
+
+    {
+        let mut duels = state.duels.lock().await;
+        let Some(duel) = duels.get_mut(duel_id) else {
+            return Ok(());
+        };
+        duel.questions = questions;
+        let now = Instant::now();
+        for player in [challenger, opponent] {
+            duel.players.insert(player.to_string(), DuelProgress {
+                started_at: Some(now),
+                ..Default::default()
+            });
+        }
+    }
+
+    update_challenge(&token, channel_id, challenge_ts, interaction, json!({
+        "text": "Duel accepted!",
+        "blocks": [mrkdwn_block(&format!(
+            "⚔️ <@{}> accepted <@{}>'s challenge! {} questions are on their way by DM — fastest accurate player wins.",
+            opponent, challenger, total
+        ))],
+    }))
+    .await?;
+
+    for player in [challenger, opponent] {
+        post_message(&token, player, duel_question_blocks(duel_id, &first, 0, total)).await?;
+        metrics().question_posted("duel");
+    }
+
+    Ok(())
+}
+
+/// Picks questions neither player has seen recently, within what the channel allows, and records
+/// them as served to both.
+async fn pick_duel_questions(
+    state: &AppState,
+    channel_id: &str,
+    challenger: &str,
+    opponent: &str,
+) -> Result<Vec<SATQuestion>> {
+    let allowed = channel_filter(state, Some(channel_id)).await;
+    let bank: Vec<SATQuestion> = load_question_bank(state).await?.into_iter().filter(|q| allowed(q)).collect();
+    let now = now_unix();
+    let window = state.config.repeat_window_secs(None);
+    let questions = state
+        .store
+        .read(|data| {
+            let mut seen = recently_seen(data, Some(challenger), None, now, window);
+            seen.extend(recently_seen(data, Some(opponent), None, now, window));
+            choose_preferring_unseen(&bank, &seen, DUEL_QUESTION_COUNT)
+        })
+        .await;
+    let ids: Vec<&str> = questions.iter().map(|q| q.id.as_str()).collect();
+    state
+        .store
+        .update(|data| {
+            record_served(data, Some(challenger), None, &ids, now, window);
+            record_served(data, Some(opponent), None, &ids, now, window);
+        })
+        .await?;
+    Ok(questions)
+}
+
+async fn decline_duel(state: &AppState, interaction: &SlackInteraction, duel_id: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+
+    let duel = {
+        let mut duels = state.duels.lock().await;
+        match duels.get(duel_id) {
+            Some(duel) if duel.opponent == interaction.user.id => duels.remove(duel_id),
+            Some(_) => {
+                drop(duels);
+                return respond_ephemeral(&interaction.response_url, "This challenge isn't for you.").await;
+            }
+            None => None,
+        }
+    };
+
+    if let Some(duel) = duel {
+        update_challenge(&token, &duel.channel_id, &duel.challenge_ts, interaction, json!({
+            "text": "Duel declined",
+            "blocks": [mrkdwn_block(&format!(
+                "🏳️ <@{}> declined <@{}>'s challenge.",
+                duel.opponent, duel.challenger
+            ))],
+        }))
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Drops challenges nobody accepted and duels nobody finished within [`DUEL_TTL`], marking
+/// unanswered challenges as expired in the channel.
+pub async fn expire_duels(state: &AppState) {
+    let expired: Vec<Duel> = {
+        let mut duels = state.duels.lock().await;
+        let ids: Vec<String> = duels
+            .values()
+            .filter(|d| d.created_at.elapsed() > DUEL_TTL)
+            .map(|d| d.id.clone())
+            .collect();
+        ids.iter().filter_map(|id| duels.remove(id)).collect()
+    };
+
+    let token = state.config.slack.bot_token.clone();
+    for duel in expired.iter().filter(|d| !d.accepted && !d.challenge_ts.is_empty()) {
+        let message = json!({
+            "text": "Duel expired",
+            "blocks": [mrkdwn_block(&format!(
+                "⌛ <@{}>'s challenge to <@{}> expired.",
+                duel.challenger, duel.opponent
+            ))],
+        });
+        if let Err(e) = update_message(&token, &duel.channel_id, &duel.challenge_ts, &message).await {
+            tracing::warn!("Failed to mark duel {} as expired: {}", duel.id, e);
+        }
+    }
+}
+
+/// Rewrites the challenge message. Without a `ts` it was a `response_url` reply, which only the
+/// button's own `response_url` can replace.
+async fn update_challenge(
+    token: &str,
+    channel_id: &str,
+    challenge_ts: &str,
+    interaction: &SlackInteraction,
+    mut message: Value,
+) -> Result<()> {
+    if !challenge_ts.is_empty() {
+        return update_message(token, channel_id, challenge_ts, &message).await;
+    }
+    message["replace_original"] = Value::Bool(true);
+    respond(&interaction.response_url, &message).await
+}
+
+async fn answer_duel(state: &AppState, interaction: &SlackInteraction, value: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+
+    let mut parts = value.splitn(3, ':');
+    let (Some(duel_id), Some(index), Some(selected)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(anyhow::anyhow!("Invalid duel answer value: {}", value));
+    };
+    let index: usize = index.parse()?;
+    let user_id = &interaction.user.id;
+
+    let mut duels = state.duels.lock().await;
+    let Some(duel) = duels.get_mut(duel_id) else {
+        drop(duels);
+        return respond_ephemeral(&interaction.response_url, "This duel has already ended.").await;
+    };
+    let total = duel.questions.len();
+    let Some(question) = duel.questions.get(index).cloned() else {
+        drop(duels);
+        return respond_ephemeral(&interaction.response_url, "That question isn't part of this duel.").await;
+    };
+    let correct_answer = question.question.correct_answer.clone();
+    let Some(progress) = duel.players.get_mut(user_id) else {
+        return Ok(());
+    };
+
+    // Ignore double clicks and clicks on questions that were already graded.
+    if progress.answered != index {
+        return Ok(());
+    }
+
+    let is_correct = selected == correct_answer;
+    progress.answered += 1;
+    if is_correct {
+        progress.correct += 1;
+    }
+
+    let next = duel.questions.get(progress.answered).cloned();
+    if next.is_none() {
+        progress.elapsed = progress.started_at.map(|started| started.elapsed());
+    }
+    let progress = progress.clone();
+    let finished = duel.is_finished();
+    let duel_snapshot = duel.clone();
+    if finished {
+        duels.remove(duel_id);
+    }
+    drop(duels);
+
+    state
+        .store
+        .update(|data| {
+            record_attempt(data, Attempt {
+                user_id: user_id.clone(),
+                channel_id: duel_snapshot.channel_id.clone(),
+                question_id: question.id.clone(),
+                domain: question.domain.clone(),
+                difficulty: question.difficulty.clone(),
+                selected: selected.to_string(),
+                correct: is_correct,
+                timestamp: now_unix(),
+                source: "duel".to_string(),
+                message_ts: None,
+                hints: 0,
+            })
+        })
+        .await?;
+    if is_correct {
+        track_correct_answer(state, user_id, Some(&duel_snapshot.channel_id));
+    }
+    track_attempt(state, user_id, Some(&duel_snapshot.channel_id));
+
+    let verdict = if is_correct {
+        format!("✅ Question {} of {}: correct!", index + 1, total)
+    } else {
+        format!("❌ Question {} of {}: the answer was {}.", index + 1, total, correct_answer)
+    };
+    respond(&interaction.response_url, &json!({
+        "replace_original": true,
+        "text": verdict,
+        "blocks": [mrkdwn_block(&verdict)],
+    }))
+    .await?;
+
+    match next {
+        Some(question) => {
+            post_message(&token, user_id, duel_question_blocks(duel_id, &question, progress.answered, total)).await?;
+            metrics().question_posted("duel");
+        }
+        None => {
+            let seconds = progress.elapsed.unwrap_or_default().as_secs_f32();
+            let summary = format!(
+                "🏁 You finished with {}/{} correct in {:.1}s.{}",
+                progress.correct,
+                total,
+                seconds,
+                if finished { "" } else { " Waiting for your opponent…" }
+            );
+            post_message(&token, user_id, vec![section_block(&summary)]).await?;
+        }
+    }
+
+    if finished {
+        let result = json!({
+            "text": "Duel results",
+            "blocks": [section_block(&duel_result_text(&duel_snapshot))],
+        });
+        // Nothing follows up on the results, so no `ts` is needed.
+        deliver(
+            &token,
+            &duel_snapshot.channel_id,
+            Some(&duel_snapshot.response_url),
+            &result,
+            Delivery::ResponseUrl,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Ranks by correct answers first and total time second.
+fn duel_result_text(duel: &Duel) -> String {
+    let total = duel.questions.len();
+    let line = |user: &str| {
+        let progress = duel.players.get(user).cloned().unwrap_or_default();
+        format!(
+            "<@{}>: {}/{} correct in {:.1}s",
+            user,
+            progress.correct,
+            total,
+            progress.elapsed.unwrap_or_default().as_secs_f32()
+        )
+    };
+
+    let score = |user: &str| {
+        let progress = duel.players.get(user).cloned().unwrap_or_default();
+        (progress.correct, std::cmp::Reverse(progress.elapsed.unwrap_or_default()))
+    };
+
+    let headline = match score(&duel.challenger).cmp(&score(&duel.opponent)) {
+        std::cmp::Ordering::Greater => format!("🏆 <@{}> wins the duel!", duel.challenger),
+        std::cmp::Ordering::Less => format!("🏆 <@{}> wins the duel!", duel.opponent),
+        std::cmp::Ordering::Equal => "🤝 It's a tie!".to_string(),
+    };
+
+    format!(
+        "*⚔️ Duel results*\n{}\n• {}\n• {}",
+        headline,
+        line(&duel.challenger),
+        line(&duel.opponent)
+    )
+}
+
+fn duel_question_blocks(duel_id: &str, question: &SATQuestion, index: usize, total: usize) -> Vec<SlackBlock> {
+    let mut blocks = vec![section_block(&format!("*⚔️ Duel — question {} of {}*", index + 1, total))];
+    blocks.extend(create_question_content_blocks(question));
+    blocks.push(SlackBlock {
+        block_type: "actions".to_string(),
+        text: None,
+        elements: Some(
+            create_answer_buttons(question, "duel_answer", |letter| format!("{}:{}:{}", duel_id, index, letter))
+                .into_iter()
+                .chain(std::iter::once(create_report_overflow(question)))
+                .collect(),
+        ),
+        accessory: None,
+    });
+    blocks
+}
+
+fn challenge_blocks(duel_id: &str, challenger: &str, opponent: &str) -> Value {
+    json!([
+        mrkdwn_block(&format!(
+            "⚔️ <@{}> challenged <@{}> to an SAT duel! {} questions, same set for both, fastest accurate player wins.",
+            challenger, opponent, DUEL_QUESTION_COUNT
+        )),
+        {
+            "type": "actions",
+            "elements": [
+                {
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": "✅ Accept", "emoji": true },
+                    "style": "primary",
+                    "action_id": "duel_accept",
+                    "value": duel_id
+                },
+                {
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": "Decline", "emoji": true },
+                    "action_id": "duel_decline",
+                    "value": duel_id
+                }
+            ]
+        }
+    ])
+}
@@ -0,0 +1,177 @@
+use serde_json::json;
+use std::fmt;
+use crate::slack::respond;
+
+/// A Slack Web API call that came back with `ok: false`.
+#[derive(Debug)]
+pub struct SlackApiError {
+    pub method: String,
+    /// Slack's `error` code, e.g. `not_in_channel`.
+    pub code: String,
+    /// The scope Slack says is missing, for `missing_scope`.
+    pub needed: Option<String>,
+}
+
+impl fmt::Display for SlackApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Slack API {} failed: {}", self.method, self.code)
+    }
+}
+
+impl std::error::Error for SlackApiError {}
+
+/// A question source that couldn't be reached or didn't answer with a success status.
+#[derive(Debug)]
+pub struct UpstreamError {
+    pub source: String,
+    pub reason: String,
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Question source {} is unavailable: {}", self.source, self.reason)
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+/// A question source answered, but not with anything that parses as questions.
+#[derive(Debug)]
+pub struct BankParseError(pub String);
+
+impl fmt::Display for BankParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to parse API response: {}", self.0)
+    }
+}
+
+impl std::error::Error for BankParseError {}
+
+/// What went wrong, in terms a user can act on.
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    UpstreamDown,
+    ParseError,
+    NotInChannel,
+    ChannelNotFound,
+    MissingScope(Option<String>),
+    Other,
+}
+
+impl Failure {
+    pub fn classify(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<SlackApiError>() {
+                return match e.code.as_str() {
+                    "not_in_channel" => Self::NotInChannel,
+                    "channel_not_found" => Self::ChannelNotFound,
+                    "missing_scope" => Self::MissingScope(e.needed.clone()),
+                    _ => Self::Other,
+                };
+            }
+            if cause.is::<UpstreamError>() {
+                return Self::UpstreamDown;
+            }
+            if cause.is::<BankParseError>() {
+                return Self::ParseError;
+            }
+        }
+        Self::Other
+    }
+
+    /// The ephemeral text shown to the user. `action` finishes "Something went wrong while ...".
+    pub fn message(&self, action: &str) -> String {
+        match self {
+            Self::UpstreamDown => {
+                "⚠️ The question bank is unreachable right now. Try again in a few minutes.".to_string()
+            }
+            Self::ParseError => "⚠️ The question bank sent back something I couldn't read. \
+                                 An admin can run `slack-sat-bot bank lint` to check the sources."
+                .to_string(),
+            Self::NotInChannel => {
+                "⚠️ I'm not a member of this channel. Invite me with `/invite @satbot` and try again.".to_string()
+            }
+            Self::ChannelNotFound => "⚠️ I can't see this channel. For private channels, invite me with \
+                                      `/invite @satbot` first; in DMs, use the app's Messages tab."
+                .to_string(),
+            Self::MissingScope(Some(scope)) => format!(
+                "⚠️ The app is missing the `{}` permission. Ask a workspace admin to reinstall it with that scope.",
+                scope
+            ),
+            Self::MissingScope(None) => {
+                "⚠️ The app is missing a permission it needs. Ask a workspace admin to reinstall it.".to_string()
+            }
+            Self::Other => format!("⚠️ Something went wrong while {}. Please try again.", action),
+        }
+    }
+}
+
+/// Logs a failed background task and tells the user why through `response_url`, replacing the
+/// "Loading..." reply when there is one.
+pub async fn report_failure(response_url: &str, action: &str, err: &anyhow::Error) {
+    tracing::error!("Failed while {}: {:#}", action, err);
+    if response_url.is_empty() {
+        return;
+    }
+    let failure = Failure::classify(err);
+    if let Err(e) = respond(response_url, &json!({
+        "response_type": "ephemeral",
+        "replace_original": true,
+        "text": failure.message(action),
+    }))
+    .await
+    {
+        tracing::error!("Failed to report error to the user: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    fn slack_error(code: &str, needed: Option<&str>) -> anyhow::Error {
+        SlackApiError {
+            method: "chat.postMessage".to_string(),
+            code: code.to_string(),
+            needed: needed.map(str::to_string),
+        }
+        .into()
+    }
+
+    #[test]
+    fn classifies_slack_error_codes() {
+        assert_eq!(Failure::classify(&slack_error("not_in_channel", None)), Failure::NotInChannel);
+        assert_eq!(Failure::classify(&slack_error("channel_not_found", None)), Failure::ChannelNotFound);
+        assert_eq!(
+            Failure::classify(&slack_error("missing_scope", Some("chat:write.public"))),
+            Failure::MissingScope(Some("chat:write.public".to_string()))
+        );
+        assert_eq!(Failure::classify(&slack_error("ratelimited", None)), Failure::Other);
+    }
+
+    #[test]
+    fn classifies_question_source_errors() {
+        let upstream: anyhow::Error = UpstreamError { source: "https://example.com".to_string(), reason: "503".to_string() }.into();
+        assert_eq!(Failure::classify(&upstream), Failure::UpstreamDown);
+        let parse: anyhow::Error = BankParseError("expected value".to_string()).into();
+        assert_eq!(Failure::classify(&parse), Failure::ParseError);
+    }
+
+    #[test]
+    fn looks_through_context() {
+        let err = Err::<(), _>(slack_error("not_in_channel", None)).context("posting the question").unwrap_err();
+        assert_eq!(Failure::classify(&err), Failure::NotInChannel);
+    }
+
+    #[test]
+    fn anything_else_is_other() {
+        assert_eq!(Failure::classify(&anyhow::anyhow!("disk full")), Failure::Other);
+    }
+
+    #[test]
+    fn missing_scope_message_names_the_scope() {
+        let message = Failure::MissingScope(Some("users:read".to_string())).message("posting");
+        assert!(message.contains("`users:read`"));
+    }
+}
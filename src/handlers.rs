@@ -1,70 +1,238 @@
 use axum::{
-    extract::Form,
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{Form, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
 };
-use reqwest;
 use serde_json::json;
-use std::{collections::HashMap, env};
+use std::{collections::HashMap, sync::atomic::Ordering};
 use crate::{
+    achievements::track_attempt,
+    analytics::{
+        export_attempts, export_item_stats, filter_attempts, item_stats, token_matches, ExportFormat, ExportQuery,
+    },
+    adaptive::start_adaptive,
+    bank::load_question_bank,
+    competitions::{handle_competition_submission, start_competition, COMPETITION_CALLBACK_ID},
+    authoring::{
+        discard_author_draft, handle_author_action, handle_author_preview_submission, handle_author_submission,
+        open_author_modal,
+        AUTHOR_CALLBACK_ID, AUTHOR_PREVIEW_CALLBACK_ID,
+    },
+    digest::start_digest,
+    discussion::post_channel_question,
+    duel::{handle_duel_action, start_duel},
+    errors::report_failure,
+    hints::{handle_hint_action, hints_used},
+    history::{fetch_fresh_question, EXHAUSTED_MESSAGE},
+    home::{handle_home_action, publish_home},
+    metrics::metrics,
     models::*,
-    slack::{fetch_question, create_question_blocks, post_message},
-    utils::verify_slack_signature,
+    moderation::{handle_moderation_action, handle_report_submission, open_report_modal, REPORT_CALLBACK_ID},
+    picker::{handle_picker_action, handle_picker_submission, open_picker, PICKER_CALLBACK_ID},
+    practice::{handle_practice_action, start_practice},
+    rating::{record_attempt, register_question, user_rating},
+    review::{handle_review_action, start_review},
+    settings::{
+        answer_rejection, channel_filter, channel_settings, handle_settings_submission, open_settings_modal,
+        SETTINGS_CALLBACK_ID,
+    },
+    slack::{respond, respond_ephemeral, slack_client},
+    state::AppState,
+    stats::start_stats,
+    streaks::{start_streak, track_correct_answer},
+    store::Attempt,
+    utils::{format_text_for_slack, now_unix, verify_slack_signature},
 };
 
+/// Slack payloads are small; anything bigger than this isn't from Slack.
+const MAX_SLACK_BODY: usize = 1024 * 1024;
+
+/// Rejects requests to the `/slack/*` routes that aren't signed with the app's signing secret.
+/// Everything behind it trusts the user and channel IDs in the payload.
+pub async fn verify_slack_request(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_SLACK_BODY).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!("Failed to read request body: {}", e);
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        }
+    };
+    let secret = state.config.slack.signing_secret.as_deref().unwrap_or_default();
+    if let Err(status) = verify_slack_signature(&parts.headers, &body, secret) {
+        return status.into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
 pub async fn handle_slash_command(
-    headers: HeaderMap,
+    State(state): State<AppState>,
     Form(payload): Form<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    tracing::debug!("Received headers: {:?}", headers);
     tracing::debug!("Received payload: {:?}", payload);
-    
-    let body_str = serde_urlencoded::to_string(&payload).unwrap();
-    tracing::debug!("Request body string: {}", body_str);
-    
-    tracing::info!("Signature verification disabled - proceeding with request");
 
     let command = SlackSlashCommand {
         channel_id: payload.get("channel_id").cloned().unwrap_or_default(),
+        user_id: payload.get("user_id").cloned().unwrap_or_default(),
+        text: payload.get("text").cloned().unwrap_or_default(),
         response_url: payload.get("response_url").cloned().unwrap_or_default(),
+        trigger_id: payload.get("trigger_id").cloned().unwrap_or_default(),
+        thread_ts: payload.get("thread_ts").filter(|ts| !ts.is_empty()).cloned(),
     };
 
-    tokio::spawn(async move {
-        match fetch_question().await {
-            Ok(question) => {
+    let text = command.text.trim().to_string();
+    let (subcommand, args) = text.split_once(' ').unwrap_or((text.as_str(), ""));
+    let subcommand = subcommand.to_lowercase();
+    let label = match subcommand.as_str() {
+        "" => "picker",
+        known @ ("author" | "config" | "duel" | "practice" | "adaptive" | "review" | "streak" | "stats" | "compete" | "digest") => known,
+        _ => "question",
+    };
+    metrics().commands.with_label_values(&[label]).inc();
+    if !feature_enabled(&state, &subcommand) {
+        return Json(json!({
+            "response_type": "ephemeral",
+            "text": format!("`/sat {}` is turned off in this workspace.", subcommand),
+        }))
+        .into_response();
+    }
+    match subcommand.as_str() {
+        "" => {
+            state.spawn(move |state| async move {
+                if let Err(e) = open_picker(&state, &command.trigger_id, &command.channel_id, Some(&command.response_url)).await {
+                    report_failure(&command.response_url, "opening the question picker", &e).await;
+                }
+            });
+            return StatusCode::OK.into_response();
+        }
+        "author" => {
+            state.spawn(move |state| async move {
+                if let Err(e) = open_author_modal(&state, &command.trigger_id).await {
+                    report_failure(&command.response_url, "opening the authoring form", &e).await;
+                }
+            });
+            return StatusCode::OK.into_response();
+        }
+        "config" => {
+            state.spawn(move |state| async move {
+                if let Err(e) = open_settings_modal(&state, &command).await {
+                    report_failure(&command.response_url, "opening the channel settings", &e).await;
+                }
+            });
+            return StatusCode::OK.into_response();
+        }
+        "duel" => return Json(start_duel(&state, &command, args).await).into_response(),
+        "practice" => return Json(start_practice(&state, &command, args).await).into_response(),
+        "adaptive" => return Json(start_adaptive(&state, &command, args).await).into_response(),
+        "review" => return Json(start_review(&state, &command, args).await).into_response(),
+        "streak" => return Json(start_streak(&state, &command, args).await).into_response(),
+        "stats" => return Json(start_stats(&state, &command).await).into_response(),
+        "digest" => return Json(start_digest(&state, &command, args).await).into_response(),
+        "compete" => {
+            return match start_competition(&state, &command, args).await {
+                Some(reply) => Json(reply).into_response(),
+                None => StatusCode::OK.into_response(),
+            }
+        }
+        _ => {}
+    }
+
+    state.spawn(move |state| async move {
+        let allowed = channel_filter(&state, Some(&command.channel_id)).await;
+        let selection = fetch_fresh_question(&state, Some(&command.user_id), Some(&command.channel_id), allowed).await;
+
+        match selection {
+            Ok(Some((question, exhausted))) => {
                 tracing::info!("Successfully fetched question: {:?}", question);
-                let blocks = create_question_blocks(&question);
-                let token = env::var("SLACK_BOT_TOKEN").expect("SLACK_BOT_TOKEN must be set");
-                
-                if let Err(e) = post_message(&token, &command.channel_id, blocks).await {
-                    tracing::error!("Error posting message: {}", e);
-                } else {
-                    tracing::info!("Successfully posted message to Slack");
-                    
-                    let client = reqwest::Client::new();
-                    if let Err(e) = client
-                        .post(&command.response_url)
-                        .json(&json!({
-                            "delete_original": true
-                        }))
-                        .send()
-                        .await
-                    {
-                        tracing::error!("Failed to delete loading message: {}", e);
+                if let Err(e) = state.store.update(|data| register_question(data, &question)).await {
+                    tracing::error!("Failed to register question {}: {}", question.id, e);
+                }
+                let delivered = post_channel_question(
+                    &state,
+                    &command.channel_id,
+                    Some(&command.response_url),
+                    command.thread_ts.as_deref(),
+                    &question,
+                    true,
+                )
+                .await;
+                match delivered {
+                    Err(e) => report_failure(&command.response_url, "posting the question", &e).await,
+                    Ok(ts) => {
+                        tracing::info!("Successfully posted message to Slack");
+                        metrics().question_posted("channel");
+
+                        // A response_url reply already replaced the loading message; a posted
+                        // message leaves it behind to clean up. Either way, surface the notice
+                        // when the bank has run dry.
+                        let loading_update = match (ts, exhausted) {
+                            (None, false) => None,
+                            (None, true) => Some(json!({
+                                "response_type": "ephemeral",
+                                "replace_original": false,
+                                "text": EXHAUSTED_MESSAGE,
+                            })),
+                            (Some(_), true) => Some(json!({ "replace_original": true, "text": EXHAUSTED_MESSAGE })),
+                            (Some(_), false) => Some(json!({ "delete_original": true })),
+                        };
+                        if let Some(update) = loading_update {
+                            if let Err(e) = respond(&command.response_url, &update).await {
+                                tracing::error!("Failed to update loading message: {}", e);
+                            }
+                        }
                     }
                 }
             }
-            Err(e) => {
-                tracing::error!("Error fetching question: {}", e);
+            Ok(None) => {
+                if let Err(e) = respond(&command.response_url, &json!({
+                    "response_type": "ephemeral",
+                    "replace_original": true,
+                    "text": "No questions match this channel's settings. A channel admin can widen them with `/sat config`.",
+                }))
+                .await
+                {
+                    tracing::error!("Failed to report empty question pool: {}", e);
+                }
             }
+            Err(e) => report_failure(&command.response_url, "fetching a question", &e).await,
         }
     });
 
     (StatusCode::OK, "Loading your SAT question...").into_response()
 }
 
+pub async fn handle_event(
+    State(state): State<AppState>,
+    Json(envelope): Json<SlackEventEnvelope>,
+) -> impl IntoResponse {
+    tracing::debug!("Received event: {:?}", envelope);
+
+    if envelope.envelope_type == "url_verification" {
+        return Json(json!({ "challenge": envelope.challenge })).into_response();
+    }
+
+    let Some(event) = envelope.event else {
+        return StatusCode::OK.into_response();
+    };
+
+    if event.event_type == "app_home_opened" && event.tab.as_deref() == Some("home") {
+        if let Some(user_id) = event.user {
+            state.spawn(move |state| async move {
+                if let Err(e) = publish_home(&state, &user_id).await {
+                    tracing::error!("Failed to publish App Home for {}: {}", user_id, e);
+                }
+            });
+        }
+    }
+
+    StatusCode::OK.into_response()
+}
+
 pub async fn handle_interaction(
-    headers: HeaderMap,
+    State(state): State<AppState>,
     Form(payload): Form<HashMap<String, String>>,
 ) -> impl IntoResponse {
     tracing::debug!("Received interaction payload: {:?}", payload);
@@ -85,11 +253,53 @@ pub async fn handle_interaction(
         }
     };
 
+    if interaction.interaction_type == "view_submission" {
+        let callback_id = interaction.view.as_ref().map(|v| v.callback_id.clone()).unwrap_or_default();
+
+        // Validation errors and pushed views have to come back in this response.
+        if callback_id == AUTHOR_CALLBACK_ID {
+            return Json(handle_author_submission(&state, &interaction).await).into_response();
+        }
+        if callback_id == COMPETITION_CALLBACK_ID && state.config.features.competitions {
+            return Json(handle_competition_submission(&state, &interaction).await).into_response();
+        }
+        let close_all = callback_id == AUTHOR_PREVIEW_CALLBACK_ID;
+
+        state.spawn(move |state| async move {
+            let result = match callback_id.as_str() {
+                PICKER_CALLBACK_ID => handle_picker_submission(&state, &interaction).await,
+                SETTINGS_CALLBACK_ID => handle_settings_submission(&state, &interaction).await,
+                REPORT_CALLBACK_ID if state.config.features.reports => {
+                    handle_report_submission(&state, &interaction).await
+                }
+                AUTHOR_PREVIEW_CALLBACK_ID => handle_author_preview_submission(&state, &interaction).await,
+                other => {
+                    tracing::warn!("Unknown view submission: {}", other);
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                tracing::error!("Failed to handle view submission {}: {}", callback_id, e);
+            }
+        });
+        if close_all {
+            return Json(json!({ "response_action": "clear" })).into_response();
+        }
+        return StatusCode::OK.into_response();
+    }
+
+    if interaction.interaction_type == "view_closed" {
+        if interaction.view.as_ref().is_some_and(|v| v.callback_id == AUTHOR_PREVIEW_CALLBACK_ID) {
+            discard_author_draft(&state, &interaction).await;
+        }
+        return StatusCode::OK.into_response();
+    }
+
     if interaction.interaction_type != "block_actions" {
         return StatusCode::OK.into_response();
     }
 
-    let Some(actions) = interaction.actions else {
+    let Some(actions) = interaction.actions.clone() else {
         return StatusCode::OK.into_response();
     };
 
@@ -97,16 +307,40 @@ pub async fn handle_interaction(
         return StatusCode::OK.into_response();
     };
 
-    let client = reqwest::Client::new();
-    let token = env::var("SLACK_BOT_TOKEN").expect("SLACK_BOT_TOKEN must be set");
+    if is_session_action(&action.action_id) {
+        let interaction = interaction.clone();
+        let action = action.clone();
+        state.spawn(move |state| async move {
+            if let Err(e) = dispatch_session_action(&state, &interaction, &action).await {
+                tracing::error!("Failed to handle action {}: {}", action.action_id, e);
+            }
+        });
+        return StatusCode::OK.into_response();
+    }
+
+    let client = slack_client();
+    let token = state.config.slack.bot_token.clone();
 
     if action.action_id == "clear_message" {
+        // Ephemeral messages can't be deleted through the API, only through their response_url.
+        let Some(ts) = interaction.message.as_ref().map(|m| m.ts.clone()) else {
+            if let Err(e) = client
+                .post(&interaction.response_url)
+                .json(&json!({ "delete_original": true }))
+                .send()
+                .await
+            {
+                tracing::error!("Failed to delete ephemeral message: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+            return StatusCode::OK.into_response();
+        };
         if let Err(e) = client
             .post("https://slack.com/api/chat.delete")
             .header("Authorization", format!("Bearer {}", token))
             .json(&json!({
                 "channel": interaction.channel.id,
-                "ts": interaction.message.unwrap().ts
+                "ts": ts
             }))
             .send()
             .await
@@ -121,9 +355,11 @@ pub async fn handle_interaction(
         return StatusCode::OK.into_response();
     };
 
-    let (selected_answer, correct_answer) = match value.split_once(':') {
-        Some((selected, correct)) => (selected, correct),
-        None => {
+    // `selected:correct[:question_id]`; older messages don't carry the question ID.
+    let mut parts = value.splitn(3, ':');
+    let (selected_answer, correct_answer) = match (parts.next(), parts.next()) {
+        (Some(selected), Some(correct)) => (selected, correct),
+        _ => {
             tracing::error!("Invalid value format in button: {}", value);
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
@@ -131,6 +367,37 @@ pub async fn handle_interaction(
 
     tracing::debug!("Selected answer: {}, Correct answer: {}", selected_answer, correct_answer);
 
+    let settings = channel_settings(&state, Some(&interaction.channel.id)).await;
+    if let Some(reason) = answer_rejection(&state, &settings, &interaction).await {
+        if let Err(e) = respond_ephemeral(&interaction.response_url, &reason).await {
+            tracing::error!("Failed to send response: {}", e);
+        }
+        return StatusCode::OK.into_response();
+    }
+
+    let is_correct = selected_answer == correct_answer;
+    let question_id = parts.next();
+    let rating_note = match question_id {
+        Some(question_id) => record_answer(&state, &interaction, question_id, selected_answer, is_correct).await,
+        None => String::new(),
+    };
+    let rating_note = if settings.leaderboard { rating_note } else { String::new() };
+
+    let reveal = match settings.reveal_policy.as_str() {
+        "after_answer" => true,
+        "after_correct" => is_correct,
+        _ => false,
+    };
+    if let (true, Some(question_id)) = (reveal, question_id) {
+        let response_url = interaction.response_url.clone();
+        let question_id = question_id.to_string();
+        state.spawn(move |state| async move {
+            if let Err(e) = send_explanation(&state, &response_url, &question_id).await {
+                tracing::error!("Failed to send explanation for {}: {}", question_id, e);
+            }
+        });
+    }
+
     let response_message = if selected_answer == correct_answer {
         json!({
             "response_type": "ephemeral",
@@ -139,7 +406,7 @@ pub async fn handle_interaction(
                     "type": "section",
                     "text": {
                         "type": "mrkdwn",
-                        "text": format!("✅ Correct! Well done, <@{}>!", interaction.user.id)
+                        "text": format!("✅ Correct! Well done, <@{}>!{}", interaction.user.id, rating_note)
                     }
                 },
                 {
@@ -167,7 +434,7 @@ pub async fn handle_interaction(
                     "type": "section",
                     "text": {
                         "type": "mrkdwn",
-                        "text": format!("❌ Sorry <@{}>, that's not correct. Try again!", interaction.user.id)
+                        "text": format!("❌ Sorry <@{}>, that's not correct. Try again!{}", interaction.user.id, rating_note)
                     }
                 },
                 {
@@ -200,4 +467,197 @@ pub async fn handle_interaction(
     }
 
     StatusCode::OK.into_response()
-} 
\ No newline at end of file
+} 
+/// Follows up on a graded answer with the question's explanation.
+async fn send_explanation(state: &AppState, response_url: &str, question_id: &str) -> anyhow::Result<()> {
+    let bank = load_question_bank(state).await?;
+    let Some(question) = bank.iter().find(|q| q.id == question_id) else {
+        return Ok(());
+    };
+    respond_ephemeral(
+        response_url,
+        &format!("💡 *Explanation:* {}", format_text_for_slack(&question.question.explanation)),
+    )
+    .await
+}
+
+/// Records a graded answer and describes the rating change, if there was one.
+async fn record_answer(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    question_id: &str,
+    selected: &str,
+    correct: bool,
+) -> String {
+    let result = state
+        .store
+        .update(|data| {
+            let (domain, difficulty) = data
+                .questions
+                .get(question_id)
+                .map(|q| (q.domain.clone(), q.difficulty.clone()))
+                .unwrap_or_default();
+            let attempt = Attempt {
+                user_id: interaction.user.id.clone(),
+                channel_id: interaction.channel.id.clone(),
+                question_id: question_id.to_string(),
+                domain: domain.clone(),
+                difficulty,
+                selected: selected.to_string(),
+                correct,
+                timestamp: now_unix(),
+                source: "channel".to_string(),
+                message_ts: interaction.message_ts(),
+                hints: hints_used(data, &interaction.user.id, question_id, interaction.message_ts().as_deref()),
+            };
+            let delta = record_attempt(data, attempt);
+            let rating = user_rating(data, &interaction.user.id, &domain).rating;
+            (domain, rating, delta)
+        })
+        .await;
+
+    if result.is_ok() {
+        if correct {
+            track_correct_answer(state, &interaction.user.id, Some(&interaction.channel.id));
+        }
+        track_attempt(state, &interaction.user.id, Some(&interaction.channel.id));
+    }
+    match result {
+        Ok((domain, rating, delta)) if delta != 0.0 => {
+            format!("\nYour *{}* rating: {:.0} ({:+.0})", domain, rating, delta)
+        }
+        Ok(_) => String::new(),
+        Err(e) => {
+            tracing::error!("Failed to record attempt: {}", e);
+            String::new()
+        }
+    }
+}
+
+/// Subcommands can be switched off under `[features]` in the config.
+fn feature_enabled(state: &AppState, subcommand: &str) -> bool {
+    let features = &state.config.features;
+    match subcommand {
+        "duel" => features.duels,
+        "practice" => features.practice,
+        "adaptive" => features.adaptive,
+        "review" => features.review,
+        "author" => features.authoring,
+        "streak" => features.streaks,
+        "compete" => features.competitions,
+        "digest" => features.digests,
+        _ => true,
+    }
+}
+
+/// Actions that belong to a multi-step session and are handled off the request path.
+fn is_session_action(action_id: &str) -> bool {
+    matches!(action_id.split('_').next(), Some("duel" | "practice" | "review" | "home" | "picker" | "report" | "mod" | "author" | "hint"))
+}
+
+async fn dispatch_session_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> anyhow::Result<()> {
+    match action.action_id.split('_').next() {
+        Some("duel") => handle_duel_action(state, interaction, action).await,
+        Some("practice") => handle_practice_action(state, interaction, action).await,
+        Some("review") => handle_review_action(state, interaction, action).await,
+        Some("home") => handle_home_action(state, interaction, action).await,
+        Some("picker") => handle_picker_action(state, interaction, action).await,
+        Some("report") if state.config.features.reports => open_report_modal(state, interaction, action).await,
+        Some("mod") => handle_moderation_action(state, interaction, action).await,
+        Some("author") => handle_author_action(state, interaction, action).await,
+        Some("hint") => handle_hint_action(state, interaction, action).await,
+        _ => Ok(()),
+    }
+}
+
+/// Liveness: the process is up and serving requests.
+pub async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+/// Readiness: the question bank has loaded at least once and the store can be written.
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.bank_loaded.load(Ordering::Relaxed) {
+        return (StatusCode::SERVICE_UNAVAILABLE, "question bank not loaded".to_string());
+    }
+    if let Err(e) = state.store.check_writable().await {
+        tracing::warn!("Readiness check failed: {:#}", e);
+        return (StatusCode::SERVICE_UNAVAILABLE, "store not writable".to_string());
+    }
+    (StatusCode::OK, "ready".to_string())
+}
+
+/// `GET /admin/export/attempts`: the attempts matching the query, as CSV or JSON.
+pub async fn export_attempts_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    export_endpoint(&state, &headers, &params, "attempts", export_attempts).await
+}
+
+/// `GET /admin/export/items`: per-question statistics over the attempts matching the query.
+pub async fn export_items_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<Vec<(String, String)>>,
+) -> impl IntoResponse {
+    export_endpoint(&state, &headers, &params, "items", |attempts, format| {
+        export_item_stats(&item_stats(attempts), format)
+    })
+    .await
+}
+
+async fn export_endpoint(
+    state: &AppState,
+    headers: &HeaderMap,
+    params: &[(String, String)],
+    name: &str,
+    render: impl FnOnce(&[&Attempt], ExportFormat) -> anyhow::Result<Vec<u8>>,
+) -> axum::response::Response {
+    let Some(expected) = state.config.analytics.api_token.as_deref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !token_matches(expected, given.trim()) {
+        return (StatusCode::UNAUTHORIZED, "missing or wrong bearer token").into_response();
+    }
+
+    let query = match ExportQuery::parse(params.iter().map(|(k, v)| (k.as_str(), v.as_str()))) {
+        Ok(query) => query,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let body = state.store.read(|data| render(&filter_attempts(data, &query), query.format)).await;
+    match body {
+        Ok(body) => (
+            [
+                (header::CONTENT_TYPE, query.format.content_type().to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.{}\"", name, query.format.extension()),
+                ),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to export {}: {:#}", name, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub async fn metrics_endpoint() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+}
@@ -0,0 +1,133 @@
+use anyhow::Result;
+use crate::{
+    bank::load_question_bank,
+    models::{SATQuestion, SlackAction, SlackInteraction},
+    slack::respond_ephemeral,
+    state::AppState,
+    store::{HintUsage, StoreData},
+    utils::{format_text_for_slack, now_unix},
+};
+
+/// Domain tip, one eliminated wrong choice, then the start of the explanation.
+pub const MAX_HINTS: u8 = 3;
+/// Hint usage older than this is dropped; answers to a post that old score as unhinted.
+const HINT_TTL: u64 = 14 * 24 * 60 * 60;
+
+/// Share of the usual points a correct answer earns after `hints` hints.
+pub fn hint_multiplier(hints: u8) -> f64 {
+    1.0 - 0.25 * f64::from(hints.min(MAX_HINTS))
+}
+
+/// How many hints the user revealed on this posting of the question.
+pub fn hints_used(data: &StoreData, user_id: &str, question_id: &str, message_ts: Option<&str>) -> u8 {
+    data.hints
+        .iter()
+        .find(|h| h.user_id == user_id && h.question_id == question_id && h.message_ts.as_deref() == message_ts)
+        .map(|h| h.level)
+        .unwrap_or(0)
+}
+
+/// A general strategy for the question's domain.
+fn domain_tip(domain: &str) -> &'static str {
+    match domain.to_ascii_lowercase().as_str() {
+        "algebra" => "Write the relationship as an equation first, then isolate the unknown one step at a time. Plugging the choices back in is a fine check.",
+        "advanced math" => "Look for structure: factor, complete the square or rewrite exponents before solving. Vertex and intercept forms often give the answer directly.",
+        "problem-solving and data analysis" => "Pin down exactly what quantity is asked for and keep track of units. For percentages and rates, write the ratio out before computing.",
+        "geometry and trigonometry" => "Sketch the figure and label everything you know. Similar triangles, the Pythagorean theorem and SOH-CAH-TOA cover most of these.",
+        "information and ideas" => "Go back to the text: the answer has to be supported by what it actually says, not by what seems reasonable.",
+        "craft and structure" => "Think about the author's purpose and how each part fits the whole. For word choice, substitute each option into the sentence.",
+        "expression of ideas" => "Pick the option that best serves the stated goal. For transitions, name the relationship between the two ideas first.",
+        "standard english conventions" => "Check the sentence's structure: subject–verb agreement, complete clauses and the punctuation that joins them.",
+        _ => "Reread the question and underline what it's actually asking before looking at the choices.",
+    }
+}
+
+/// The same wrong choice for everyone, so repeated clicks and classmates see a consistent hint.
+fn eliminated_choice(question: &SATQuestion) -> (&'static str, &str) {
+    let choices = &question.question.choices;
+    let wrong: Vec<(&'static str, &str)> = [("A", &choices.a), ("B", &choices.b), ("C", &choices.c), ("D", &choices.d)]
+        .into_iter()
+        .filter(|(letter, _)| !letter.eq_ignore_ascii_case(question.question.correct_answer.trim()))
+        .map(|(letter, text)| (letter, text.as_str()))
+        .collect();
+    let pick = question.id.bytes().map(usize::from).sum::<usize>() % wrong.len().max(1);
+    wrong.get(pick).copied().unwrap_or(("A", choices.a.as_str()))
+}
+
+/// The explanation's first sentence or line.
+fn explanation_opening(question: &SATQuestion) -> String {
+    let explanation = question.question.explanation.trim();
+    let line = explanation.lines().next().unwrap_or(explanation);
+    let end = line.find(". ").map(|i| i + 1).unwrap_or(line.len());
+    format_text_for_slack(&line[..end])
+}
+
+fn hint_text(question: &SATQuestion, level: u8) -> String {
+    match level {
+        1 => format!("💡 *Hint 1 — {}:* {}", question.domain, domain_tip(&question.domain)),
+        2 => {
+            let (letter, text) = eliminated_choice(question);
+            format!("💡 *Hint 2:* You can rule out *{}* ({}).", letter, format_text_for_slack(text))
+        }
+        _ => format!("💡 *Hint 3:* {}", explanation_opening(question)),
+    }
+}
+
+/// The "💡 Hint" button: reveals the next hint to the clicker only and records it, so a correct
+/// answer on this post earns less.
+pub async fn handle_hint_action(state: &AppState, interaction: &SlackInteraction, action: &SlackAction) -> Result<()> {
+    if interaction.response_url.is_empty() {
+        return Ok(());
+    }
+    if !state.config.features.hints {
+        return respond_ephemeral(&interaction.response_url, "Hints are turned off in this workspace.").await;
+    }
+    let Some(question_id) = action.value.as_deref() else {
+        return Ok(());
+    };
+    let bank = load_question_bank(state).await?;
+    let Some(question) = bank.iter().find(|q| q.id == question_id) else {
+        return respond_ephemeral(&interaction.response_url, "That question isn't in the bank anymore.").await;
+    };
+
+    let user_id = interaction.user.id.as_str();
+    let message_ts = interaction.message_ts();
+    let level = state
+        .store
+        .update(|data| {
+            let now = now_unix();
+            data.hints.retain(|h| now.saturating_sub(h.used_at) < HINT_TTL);
+            let usage = data
+                .hints
+                .iter_mut()
+                .find(|h| h.user_id == user_id && h.question_id == question_id && h.message_ts == message_ts);
+            match usage {
+                Some(usage) => {
+                    usage.level = (usage.level + 1).min(MAX_HINTS);
+                    usage.used_at = now;
+                    usage.level
+                }
+                None => {
+                    data.hints.push(HintUsage {
+                        user_id: user_id.to_string(),
+                        question_id: question_id.to_string(),
+                        message_ts: message_ts.clone(),
+                        level: 1,
+                        used_at: now,
+                    });
+                    1
+                }
+            }
+        })
+        .await?;
+
+    let mut lines: Vec<String> = (1..=level).map(|l| hint_text(question, l)).collect();
+    lines.push(format!(
+        "_{} hint{} used: a correct answer now earns {:.0}% of the usual points.{}_",
+        level,
+        if level == 1 { "" } else { "s" },
+        hint_multiplier(level) * 100.0,
+        if level == MAX_HINTS { " That's every hint for this one." } else { "" }
+    ));
+    respond_ephemeral(&interaction.response_url, &lines.join("\n")).await
+}
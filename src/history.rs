@@ -0,0 +1,124 @@
+use anyhow::Result;
+use rand::prelude::*;
+use std::collections::HashSet;
+use crate::{
+    bank::load_question_bank,
+    models::SATQuestion,
+    state::AppState,
+    store::{SeenQuestion, StoreData},
+    utils::now_unix,
+};
+
+/// Question IDs the user answered or was served, or that were posted in the channel, within the
+/// last `window` seconds (see `Config::repeat_window_secs`).
+pub fn recently_seen(
+    data: &StoreData,
+    user_id: Option<&str>,
+    channel_id: Option<&str>,
+    now: u64,
+    window: u64,
+) -> HashSet<String> {
+    let since = now.saturating_sub(window);
+    let mut seen = HashSet::new();
+
+    if let Some(user_id) = user_id {
+        seen.extend(
+            data.attempts
+                .iter()
+                .filter(|a| a.user_id == user_id && a.timestamp >= since)
+                .map(|a| a.question_id.clone()),
+        );
+        if let Some(history) = data.user_history.get(user_id) {
+            seen.extend(history.iter().filter(|s| s.at >= since).map(|s| s.question_id.clone()));
+        }
+    }
+
+    if let Some(history) = channel_id.and_then(|c| data.channel_history.get(c)) {
+        seen.extend(history.iter().filter(|s| s.at >= since).map(|s| s.question_id.clone()));
+    }
+
+    seen
+}
+
+/// Notes that questions were shown, dropping entries that have aged out of the window.
+pub fn record_served(
+    data: &mut StoreData,
+    user_id: Option<&str>,
+    channel_id: Option<&str>,
+    question_ids: &[&str],
+    now: u64,
+    window: u64,
+) {
+    let since = now.saturating_sub(window);
+    let push = |history: &mut Vec<SeenQuestion>| {
+        history.retain(|s| s.at >= since);
+        history.extend(question_ids.iter().map(|id| SeenQuestion {
+            question_id: id.to_string(),
+            at: now,
+        }));
+    };
+
+    if let Some(user_id) = user_id {
+        push(data.user_history.entry(user_id.to_string()).or_default());
+    }
+    if let Some(channel_id) = channel_id {
+        push(data.channel_history.entry(channel_id.to_string()).or_default());
+    }
+}
+
+/// The pool without seen questions, or the whole pool again once everything has been seen.
+/// The flag is `true` when the pool was exhausted and repeats are being served.
+pub fn unseen_or_all<'a>(pool: &[&'a SATQuestion], seen: &HashSet<String>) -> (Vec<&'a SATQuestion>, bool) {
+    let fresh: Vec<&SATQuestion> = pool.iter().copied().filter(|q| !seen.contains(&q.id)).collect();
+    if fresh.is_empty() {
+        (pool.to_vec(), !pool.is_empty())
+    } else {
+        (fresh, false)
+    }
+}
+
+/// Picks `count` questions, preferring unseen ones and topping up with repeats only when needed.
+pub fn choose_preferring_unseen<'a>(
+    pool: impl IntoIterator<Item = &'a SATQuestion>,
+    seen: &HashSet<String>,
+    count: usize,
+) -> Vec<SATQuestion> {
+    let mut rng = rand::thread_rng();
+    let (fresh, repeats): (Vec<&SATQuestion>, Vec<&SATQuestion>) = pool.into_iter().partition(|q| !seen.contains(&q.id));
+
+    let mut chosen: Vec<SATQuestion> = fresh.choose_multiple(&mut rng, count).map(|q| (*q).clone()).collect();
+    if chosen.len() < count {
+        let missing = count - chosen.len();
+        chosen.extend(repeats.choose_multiple(&mut rng, missing).map(|q| (*q).clone()));
+    }
+    chosen
+}
+
+/// Fetches the bank and picks one question matching `filter` that neither the user nor the channel
+/// has seen recently, then records it as served. The flag is `true` when repeats had to be used.
+pub async fn fetch_fresh_question(
+    state: &AppState,
+    user_id: Option<&str>,
+    channel_id: Option<&str>,
+    filter: impl Fn(&SATQuestion) -> bool,
+) -> Result<Option<(SATQuestion, bool)>> {
+    let bank = load_question_bank(state).await?;
+    let pool: Vec<&SATQuestion> = bank.iter().filter(|q| filter(q)).collect();
+    let now = now_unix();
+    let window = state.config.repeat_window_secs(channel_id);
+
+    let seen = state.store.read(|data| recently_seen(data, user_id, channel_id, now, window)).await;
+    let (candidates, exhausted) = unseen_or_all(&pool, &seen);
+    let Some(question) = candidates.choose(&mut rand::thread_rng()).map(|q| (*q).clone()) else {
+        return Ok(None);
+    };
+
+    state
+        .store
+        .update(|data| record_served(data, user_id, channel_id, &[question.id.as_str()], now, window))
+        .await?;
+    Ok(Some((question, exhausted)))
+}
+
+pub const EXHAUSTED_MESSAGE: &str =
+    "🎉 You've completed every question matching your filters! Serving repeats until new questions are added.";
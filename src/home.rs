@@ -0,0 +1,189 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use crate::{
+    achievements::achievement_lines,
+    bank::load_question_bank,
+    history::{fetch_fresh_question, EXHAUSTED_MESSAGE},
+    metrics::metrics,
+    models::*,
+    rating::register_question,
+    slack::{create_question_blocks, mrkdwn_block, post_message, section_block, slack_api},
+    state::AppState,
+    stats::{summarize_user, UserSummary},
+    utils::now_unix,
+};
+
+const DIFFICULTIES: [&str; 3] = ["Easy", "Medium", "Hard"];
+
+pub async fn publish_home(state: &AppState, user_id: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let bank = load_question_bank(state).await?;
+    let domains: BTreeSet<String> = bank.iter().map(|q| q.domain.clone()).collect();
+    let (summary, achievements) = state
+        .store
+        .read(|data| (summarize_user(data, user_id, now_unix()), achievement_lines(data, &state.config, user_id)))
+        .await;
+
+    slack_api(&token, "views.publish", &json!({
+        "user_id": user_id,
+        "view": {
+            "type": "home",
+            "blocks": home_blocks(&summary, &achievements, &domains),
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+pub async fn handle_home_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    match action.action_id.as_str() {
+        "home_practice" => {
+            // Overflow options carry `domain|difficulty`; an empty difficulty means any.
+            let selected = action.selected_option.as_ref().map(|o| o.value.clone()).unwrap_or_default();
+            let (domain, difficulty) = selected.split_once('|').unwrap_or((selected.as_str(), ""));
+            send_practice_question(state, &interaction.user.id, domain, difficulty).await
+        }
+        "home_refresh" => publish_home(state, &interaction.user.id).await,
+        other => {
+            tracing::warn!("Unknown home action: {}", other);
+            Ok(())
+        }
+    }
+}
+
+async fn send_practice_question(state: &AppState, user_id: &str, domain: &str, difficulty: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let selected = fetch_fresh_question(state, Some(user_id), None, |q| {
+        q.domain == domain && (difficulty.is_empty() || q.difficulty.eq_ignore_ascii_case(difficulty))
+    })
+    .await?;
+
+    let Some((question, exhausted)) = selected else {
+        let label = if difficulty.is_empty() { String::new() } else { format!("{} ", difficulty.to_lowercase()) };
+        post_message(&token, user_id, vec![section_block(&format!("No {}questions found in *{}*.", label, domain))])
+            .await?;
+        return Ok(());
+    };
+
+    state.store.update(|data| register_question(data, &question)).await?;
+
+    let mut blocks = Vec::new();
+    if exhausted {
+        blocks.push(section_block(EXHAUSTED_MESSAGE));
+    }
+    blocks.extend(create_question_blocks(&question, state.config.features.hints));
+    post_message(&token, user_id, blocks).await?;
+    metrics().question_posted("home");
+    Ok(())
+}
+
+fn home_blocks(summary: &UserSummary, achievements: &(Vec<String>, usize), domains: &BTreeSet<String>) -> Vec<Value> {
+    let mut blocks = vec![
+        json!({
+            "type": "header",
+            "text": { "type": "plain_text", "text": "📊 Your SAT dashboard", "emoji": true }
+        }),
+        mrkdwn_block(&format!(
+            "*Answered:* {}   *Accuracy:* {:.0}%   *Streak:* 🔥 {} day{}   *Due for review:* {}",
+            summary.attempts,
+            summary.accuracy() * 100.0,
+            summary.streak_days,
+            if summary.streak_days == 1 { "" } else { "s" },
+            summary.due_reviews
+        )),
+    ];
+
+    if summary.due_reviews > 0 {
+        blocks.push(mrkdwn_block(&format!(
+            "📚 You have {} question{} due — run `/sat review` to work through them.",
+            summary.due_reviews,
+            if summary.due_reviews == 1 { "" } else { "s" }
+        )));
+    }
+
+    let weakest = summary.weakest_domains(3);
+    if !weakest.is_empty() {
+        let lines = weakest
+            .iter()
+            .map(|d| format!("• {} — {}/{} ({:.0}%)", d.domain, d.correct, d.attempts, d.accuracy() * 100.0))
+            .collect::<Vec<_>>()
+            .join("\n");
+        blocks.push(mrkdwn_block(&format!("*🎯 Focus areas*\n{}", lines)));
+    }
+
+    let (earned, remaining) = achievements;
+    if !earned.is_empty() {
+        blocks.push(mrkdwn_block(&format!(
+            "*🏅 Achievements* ({} still locked)\n{}",
+            remaining,
+            earned.join("\n")
+        )));
+    }
+
+    if !summary.recent.is_empty() {
+        let lines = summary
+            .recent
+            .iter()
+            .map(|a| {
+                format!(
+                    "{} {} · {} · <!date^{}^{{date_short}} {{time}}|recently>",
+                    if a.correct { "✅" } else { "❌" },
+                    if a.domain.is_empty() { "Unknown domain" } else { &a.domain },
+                    a.difficulty,
+                    a.timestamp
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        blocks.push(mrkdwn_block(&format!("*🕘 Recent attempts*\n{}", lines)));
+    }
+
+    blocks.push(json!({ "type": "divider" }));
+    blocks.push(mrkdwn_block("*✏️ Practice* — pick a domain and difficulty; the question arrives in your DMs."));
+
+    for domain in domains {
+        let progress = summary
+            .domains
+            .iter()
+            .find(|d| &d.domain == domain)
+            .map(|d| format!(" — {}/{} correct, rating {:.0}", d.correct, d.attempts, d.rating))
+            .unwrap_or_default();
+        let options: Vec<Value> = std::iter::once(("Any difficulty", ""))
+            .chain(DIFFICULTIES.iter().map(|d| (*d, *d)))
+            .map(|(label, difficulty)| {
+                json!({
+                    "text": { "type": "plain_text", "text": label },
+                    "value": format!("{}|{}", domain, difficulty),
+                })
+            })
+            .collect();
+
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": format!("*{}*{}", domain, progress) },
+            "accessory": {
+                "type": "overflow",
+                "action_id": "home_practice",
+                "options": options,
+            }
+        }));
+    }
+
+    // Home tabs are limited to 100 blocks; keep room for the Refresh button.
+    blocks.truncate(99);
+    blocks.push(json!({
+        "type": "actions",
+        "elements": [{
+            "type": "button",
+            "text": { "type": "plain_text", "text": "🔄 Refresh", "emoji": true },
+            "action_id": "home_refresh",
+            "value": "refresh"
+        }]
+    }));
+    blocks
+}
@@ -1,9 +1,39 @@
 pub mod models;
+pub mod config;
 pub mod handlers;
 pub mod utils;
 pub mod slack;
+pub mod state;
+pub mod duel;
+pub mod practice;
+pub mod store;
+pub mod rating;
+pub mod adaptive;
+pub mod review;
+pub mod scheduler;
+pub mod history;
+pub mod stats;
+pub mod home;
+pub mod picker;
+pub mod bank;
+pub mod moderation;
+pub mod authoring;
+pub mod bank_io;
+pub mod cli;
+pub mod lint;
+pub mod settings;
+pub mod metrics;
+pub mod errors;
+pub mod discussion;
+pub mod streaks;
+pub mod achievements;
+pub mod competitions;
+pub mod digest;
+pub mod analytics;
+pub mod hints;
 
 pub use models::*;
 pub use handlers::*;
 pub use utils::*;
-pub use slack::*; 
\ No newline at end of file
+pub use slack::*;
+pub use state::*; 
\ No newline at end of file
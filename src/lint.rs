@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashSet};
+use crate::{bank::load_full_bank, bank_io::check_latex, models::SATQuestion, state::AppState};
+
+const LETTERS: [&str; 4] = ["A", "B", "C", "D"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth cleaning up, but the question still works.
+    Warning,
+    /// The question can't be answered or rendered correctly.
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub question_id: String,
+    pub message: String,
+}
+
+/// A named check over one question.
+pub struct Rule {
+    pub name: &'static str,
+    pub severity: Severity,
+    check: fn(&SATQuestion) -> Option<String>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule { name: "missing-id", severity: Severity::Error, check: missing_id },
+    Rule { name: "missing-domain", severity: Severity::Error, check: missing_domain },
+    Rule { name: "empty-question", severity: Severity::Error, check: empty_question },
+    Rule { name: "missing-choice", severity: Severity::Error, check: missing_choice },
+    Rule { name: "answer-key", severity: Severity::Error, check: answer_key },
+    Rule { name: "latex", severity: Severity::Error, check: latex },
+    Rule { name: "invalid-svg", severity: Severity::Error, check: invalid_svg },
+    Rule { name: "empty-explanation", severity: Severity::Warning, check: empty_explanation },
+    Rule { name: "null-paragraph", severity: Severity::Warning, check: null_paragraph },
+    Rule { name: "null-visuals", severity: Severity::Warning, check: null_visuals },
+];
+
+/// Name of the bank-wide rule that flags every repeat of an ID after its first occurrence.
+pub const DUPLICATE_ID: &str = "duplicate-id";
+
+/// Runs every rule against a single question.
+pub fn lint_question(question: &SATQuestion) -> Vec<Finding> {
+    RULES
+        .iter()
+        .filter_map(|rule| {
+            (rule.check)(question).map(|message| Finding {
+                rule: rule.name,
+                severity: rule.severity,
+                question_id: question.id.clone(),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Runs every rule against the bank, plus the duplicate ID check.
+pub fn lint_bank(bank: &[SATQuestion]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut ids = HashSet::new();
+    for question in bank {
+        findings.extend(lint_question(question));
+        if !ids.insert(question.id.as_str()) {
+            findings.push(Finding {
+                rule: DUPLICATE_ID,
+                severity: Severity::Error,
+                question_id: question.id.clone(),
+                message: "ID appears more than once; later copies are ignored".to_string(),
+            });
+        }
+    }
+    findings
+}
+
+/// Drops questions with error-level findings. Duplicates keep their first copy.
+pub fn exclude_failing(bank: Vec<SATQuestion>) -> Vec<SATQuestion> {
+    let mut ids = HashSet::new();
+    bank.into_iter()
+        .filter(|q| lint_question(q).iter().all(|f| f.severity < Severity::Error))
+        .filter(|q| ids.insert(q.id.clone()))
+        .collect()
+}
+
+/// A plain-text report with findings grouped by rule, errors first.
+pub fn format_report(findings: &[Finding], total: usize) -> String {
+    let mut by_rule: BTreeMap<(std::cmp::Reverse<Severity>, &str), Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        by_rule.entry((std::cmp::Reverse(finding.severity), finding.rule)).or_default().push(finding);
+    }
+
+    let failing: HashSet<&str> = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .map(|f| f.question_id.as_str())
+        .collect();
+    let mut report = format!(
+        "Linted {} question(s): {} with errors, {} finding(s) across {} rule(s)\n",
+        total,
+        failing.len(),
+        findings.len(),
+        by_rule.len()
+    );
+    for ((severity, rule), group) in &by_rule {
+        let label = if severity.0 == Severity::Error { "error" } else { "warning" };
+        report.push_str(&format!("\n[{}] {} ({})\n", label, rule, group.len()));
+        for finding in group {
+            report.push_str(&format!("  {}: {}\n", finding.question_id, finding.message));
+        }
+    }
+    report
+}
+
+/// Lints the bank once at startup and logs the report, so data problems show up in the logs
+/// without anyone having to run `bank lint`.
+pub async fn startup_check(state: &AppState) {
+    let bank = match load_full_bank(state).await {
+        Ok(bank) => bank,
+        Err(e) => {
+            tracing::warn!("Skipping question bank lint: {}", e);
+            return;
+        }
+    };
+    let findings = lint_bank(&bank);
+    if findings.is_empty() {
+        tracing::info!("Question bank lint: {} question(s), no findings", bank.len());
+        return;
+    }
+    tracing::warn!("Question bank lint:\n{}", format_report(&findings, bank.len()));
+    if state.config.features.lint_exclude {
+        tracing::info!("features.lint_exclude is on; questions with errors are out of rotation");
+    }
+}
+
+fn missing_id(q: &SATQuestion) -> Option<String> {
+    q.id.trim().is_empty().then(|| "id is missing".to_string())
+}
+
+fn missing_domain(q: &SATQuestion) -> Option<String> {
+    q.domain.trim().is_empty().then(|| "domain is missing".to_string())
+}
+
+fn empty_question(q: &SATQuestion) -> Option<String> {
+    q.question.question.trim().is_empty().then(|| "question text is missing".to_string())
+}
+
+fn missing_choice(q: &SATQuestion) -> Option<String> {
+    let c = &q.question.choices;
+    let missing: Vec<&str> = LETTERS
+        .iter()
+        .zip([&c.a, &c.b, &c.c, &c.d])
+        .filter(|(_, text)| text.trim().is_empty())
+        .map(|(letter, _)| *letter)
+        .collect();
+    (!missing.is_empty()).then(|| format!("choice {} is missing", missing.join(", ")))
+}
+
+fn answer_key(q: &SATQuestion) -> Option<String> {
+    let answer = &q.question.correct_answer;
+    (!LETTERS.contains(&answer.as_str())).then(|| format!("correct_answer `{}` is not one of A, B, C, D", answer))
+}
+
+fn latex(q: &SATQuestion) -> Option<String> {
+    let text = &q.question;
+    [
+        ("paragraph", text.paragraph.as_deref().unwrap_or_default()),
+        ("question", text.question.as_str()),
+        ("choice A", text.choices.a.as_str()),
+        ("choice B", text.choices.b.as_str()),
+        ("choice C", text.choices.c.as_str()),
+        ("choice D", text.choices.d.as_str()),
+        ("explanation", text.explanation.as_str()),
+    ]
+    .into_iter()
+    .find_map(|(field, value)| check_latex(value).err().map(|e| format!("{}: {}", field, e)))
+}
+
+fn invalid_svg(q: &SATQuestion) -> Option<String> {
+    let visuals = q.visuals.as_ref()?;
+    match (visuals.visual_type.as_deref(), visuals.svg_content.as_deref()) {
+        (Some("svg"), None) => Some("visual type is `svg` but svg_content is empty".to_string()),
+        (_, Some(svg)) if !is_placeholder(svg) => {
+            roxmltree::Document::parse(svg).err().map(|e| format!("svg_content does not parse: {}", e))
+        }
+        _ => None,
+    }
+}
+
+fn empty_explanation(q: &SATQuestion) -> Option<String> {
+    q.question.explanation.trim().is_empty().then(|| "explanation is empty".to_string())
+}
+
+fn null_paragraph(q: &SATQuestion) -> Option<String> {
+    q.question
+        .paragraph
+        .as_deref()
+        .is_some_and(is_placeholder)
+        .then(|| "paragraph is the string \"null\" rather than absent".to_string())
+}
+
+fn null_visuals(q: &SATQuestion) -> Option<String> {
+    let visuals = q.visuals.as_ref()?;
+    let placeholders: Vec<&str> = [("type", &visuals.visual_type), ("svg_content", &visuals.svg_content)]
+        .into_iter()
+        .filter(|(_, value)| value.as_deref().is_some_and(is_placeholder))
+        .map(|(field, _)| field)
+        .collect();
+    (!placeholders.is_empty()).then(|| format!("visuals {} is the string \"null\"", placeholders.join(" and ")))
+}
+
+/// Upstream sometimes writes the string `"null"` where it means no value.
+fn is_placeholder(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("null")
+}
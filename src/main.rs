@@ -1,13 +1,25 @@
 use anyhow::Result;
 use axum::{
-    routing::post,
+    middleware,
+    routing::{get, post},
     Router,
 };
-use std::net::SocketAddr;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use dotenv::dotenv;
-use slack_sat_bot::handlers::{handle_slash_command, handle_interaction};
+use slack_sat_bot::{
+    handlers::{
+        export_attempts_endpoint, export_items_endpoint, handle_event, handle_slash_command, handle_interaction, healthz,
+        metrics_endpoint, readyz, verify_slack_request,
+    },
+    cli,
+    config::Config,
+    lint::startup_check,
+    practice::restore_sessions,
+    scheduler::spawn_scheduler,
+    state::AppState,
+    store::Store,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -20,14 +32,90 @@ async fn main() -> Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let app = Router::new()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = Config::load()?;
+    config.validate(args.is_empty())?;
+
+    let store = Store::open(&config.store_path)?;
+    tracing::info!("using store at {}", config.store_path.display());
+    let addr = config.bind_address;
+    let state = AppState::new(config, store);
+
+    if !args.is_empty() {
+        return cli::run(&state, &args).await;
+    }
+
+    let _store_lock = state.store.lock()?;
+    // Saved practice modules must be back before any button click or deadline job can look them up.
+    restore_sessions(&state).await;
+    spawn_scheduler(&state);
+    state.spawn(|state| async move { startup_check(&state).await });
+
+    let slack = Router::new()
         .route("/slack/commands", post(handle_slash_command))
         .route("/slack/interactions", post(handle_interaction))
-        .layer(TraceLayer::new_for_http());
+        .route("/slack/events", post(handle_event))
+        .route_layer(middleware::from_fn_with_state(state.clone(), verify_slack_request));
+
+    let app = Router::new()
+        .merge(slack)
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics_endpoint))
+        .route("/admin/export/attempts", get(export_attempts_endpoint))
+        .route("/admin/export/items", get(export_items_endpoint))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state.clone());
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     tracing::info!("listening on {}", addr);
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
+    axum::serve(tokio::net::TcpListener::bind(addr).await?, app)
+        .with_graceful_shutdown({
+            let shutdown = state.shutdown.clone();
+            async move {
+                shutdown_signal().await;
+                shutdown.cancel();
+            }
+        })
+        .await?;
+
+    // Requests have stopped; give background work a chance to finish posting.
+    state.tasks.close();
+    let drain = state.config.drain_timeout();
+    tracing::info!("draining {} background task(s) for up to {}s", state.tasks.len(), drain.as_secs());
+    if tokio::time::timeout(drain, state.tasks.wait()).await.is_err() {
+        tracing::warn!("shutting down with {} background task(s) still running", state.tasks.len());
+    }
 
     Ok(())
+}
+
+/// Resolves on SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("shutdown signal received");
 } 
\ No newline at end of file
@@ -0,0 +1,90 @@
+use prometheus::{
+    histogram_opts, opts, Encoder, HistogramVec, IntCounterVec, IntGauge, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+
+/// Process-wide counters exposed on `/metrics`. Global because the Slack and upstream helpers
+/// that record into it don't take app state.
+pub struct Metrics {
+    registry: Registry,
+    /// Slash commands by subcommand.
+    pub commands: IntCounterVec,
+    /// Questions sent to Slack by mode (`channel`, `picker`, `duel`, ...).
+    pub questions_posted: IntCounterVec,
+    /// Graded answers by mode and correctness.
+    pub answers: IntCounterVec,
+    /// Failed Slack Web API calls by method and error code.
+    pub slack_api_errors: IntCounterVec,
+    /// Time spent fetching each upstream question source.
+    pub upstream_fetch_seconds: HistogramVec,
+    /// Questions in the bank as of the last successful load.
+    pub bank_size: IntGauge,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let commands = IntCounterVec::new(
+            opts!("sat_commands_received_total", "Slash commands received"),
+            &["subcommand"],
+        )
+        .unwrap();
+        let questions_posted = IntCounterVec::new(
+            opts!("sat_questions_posted_total", "Questions sent to Slack"),
+            &["mode"],
+        )
+        .unwrap();
+        let answers = IntCounterVec::new(
+            opts!("sat_answers_graded_total", "Answers graded"),
+            &["mode", "correct"],
+        )
+        .unwrap();
+        let slack_api_errors = IntCounterVec::new(
+            opts!("sat_slack_api_errors_total", "Failed Slack Web API calls"),
+            &["method", "error"],
+        )
+        .unwrap();
+        let upstream_fetch_seconds = HistogramVec::new(
+            histogram_opts!("sat_upstream_fetch_seconds", "Upstream question source fetch latency"),
+            &["source", "outcome"],
+        )
+        .unwrap();
+        let bank_size = IntGauge::new("sat_question_bank_size", "Questions in the bank").unwrap();
+
+        registry.register(Box::new(commands.clone())).unwrap();
+        registry.register(Box::new(questions_posted.clone())).unwrap();
+        registry.register(Box::new(answers.clone())).unwrap();
+        registry.register(Box::new(slack_api_errors.clone())).unwrap();
+        registry.register(Box::new(upstream_fetch_seconds.clone())).unwrap();
+        registry.register(Box::new(bank_size.clone())).unwrap();
+
+        Self {
+            registry,
+            commands,
+            questions_posted,
+            answers,
+            slack_api_errors,
+            upstream_fetch_seconds,
+            bank_size,
+        }
+    }
+
+    pub fn question_posted(&self, mode: &str) {
+        self.questions_posted.with_label_values(&[mode]).inc();
+    }
+
+    /// Everything in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
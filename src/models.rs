@@ -1,18 +1,25 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub struct Visuals {
     #[serde(rename = "type")]
-    #[serde(default = "default_null_string")]
-    pub visual_type: String,
-    #[serde(default = "default_null_string")]
-    pub svg_content: String,
+    #[serde(default, deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub visual_type: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub svg_content: Option<String>,
+}
+
+impl Visuals {
+    pub fn is_empty(&self) -> bool {
+        self.visual_type.is_none() && self.svg_content.is_none()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Question {
-    #[serde(default = "default_null_string")]
-    pub paragraph: String,
+    #[serde(default, deserialize_with = "empty_as_none", skip_serializing_if = "Option::is_none")]
+    pub paragraph: Option<String>,
     pub question: String,
     pub choices: Choices,
     pub correct_answer: String,
@@ -35,8 +42,8 @@ pub struct Choices {
 pub struct SATQuestion {
     pub id: String,
     pub domain: String,
-    #[serde(default)]
-    pub visuals: Visuals,
+    #[serde(default, deserialize_with = "visuals_or_none", skip_serializing_if = "Option::is_none")]
+    pub visuals: Option<Visuals>,
     pub question: Question,
     pub difficulty: String,
 }
@@ -71,10 +78,15 @@ pub struct SlackText {
 pub struct SlackElement {
     #[serde(rename = "type")]
     pub element_type: String,
-    pub text: SlackText,
+    /// Overflow menus have options instead of a label.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<SlackText>,
+    #[serde(default)]
     pub action_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<SlackOption>>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -86,7 +98,12 @@ pub struct SlackMessage {
 #[derive(Debug, Deserialize)]
 pub struct SlackSlashCommand {
     pub channel_id: String,
+    pub user_id: String,
+    pub text: String,
     pub response_url: String,
+    pub trigger_id: String,
+    /// Set when the command was run inside a thread.
+    pub thread_ts: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -95,14 +112,40 @@ pub struct SlackInteraction {
     pub interaction_type: String,
     pub user: SlackUser,
     pub actions: Option<Vec<SlackAction>>,
+    /// Absent for actions taken in views such as the App Home tab.
+    #[serde(default)]
     pub response_url: String,
     pub message: Option<SlackMessage>,
+    #[serde(default)]
     pub channel: SlackChannel,
+    #[serde(default)]
+    pub container: Option<SlackContainer>,
+    #[serde(default)]
+    pub trigger_id: Option<String>,
+    /// Present on `view_submission` interactions.
+    #[serde(default)]
+    pub view: Option<SlackView>,
+}
+
+impl SlackInteraction {
+    /// The `ts` of the message the action came from; ephemeral messages only carry it on the container.
+    pub fn message_ts(&self) -> Option<String> {
+        self.message
+            .as_ref()
+            .map(|m| m.ts.clone())
+            .or_else(|| self.container.as_ref().and_then(|c| c.message_ts.clone()))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SlackContainer {
+    pub message_ts: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SlackUser {
     pub id: String,
+    #[serde(default)]
     pub username: String,
 }
 
@@ -112,6 +155,16 @@ pub struct SlackAction {
     pub action_type: String,
     pub action_id: String,
     pub value: Option<String>,
+    /// Set instead of `value` by selects and overflow menus.
+    #[serde(default)]
+    pub selected_option: Option<SlackOption>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlackOption {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<SlackText>,
+    pub value: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -127,17 +180,126 @@ pub struct SlackMessageText {
     pub text: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct SlackChannel {
     pub id: String,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct SlackView {
+    pub id: String,
+    #[serde(default)]
+    pub callback_id: String,
+    #[serde(default)]
+    pub private_metadata: String,
+    pub state: Option<SlackViewState>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SlackViewState {
+    /// block id -> action id -> value
+    pub values: HashMap<String, HashMap<String, SlackViewValue>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SlackViewValue {
+    #[serde(rename = "type")]
+    pub value_type: String,
+    pub value: Option<String>,
+    pub selected_option: Option<SlackOption>,
+    /// Multi-selects and checkboxes.
+    #[serde(default)]
+    pub selected_options: Option<Vec<SlackOption>>,
+    #[serde(default)]
+    pub selected_users: Option<Vec<String>>,
+    #[serde(default)]
+    pub selected_conversations: Option<Vec<String>>,
+    /// Datepickers, as `YYYY-MM-DD`.
+    #[serde(default)]
+    pub selected_date: Option<String>,
+}
+
+impl SlackView {
+    /// The submitted value of an input block, whether typed or selected.
+    pub fn input_value(&self, block_id: &str) -> Option<String> {
+        self.state
+            .as_ref()?
+            .values
+            .get(block_id)?
+            .values()
+            .next()
+            .and_then(|v| {
+                v.selected_option
+                    .as_ref()
+                    .map(|o| o.value.clone())
+                    .or_else(|| v.selected_date.clone())
+                    .or_else(|| v.value.clone())
+            })
+    }
+
+    /// Every selected value of a multi-select or checkbox input block, including user and
+    /// conversation pickers.
+    pub fn input_values(&self, block_id: &str) -> Vec<String> {
+        let Some(v) = self
+            .state
+            .as_ref()
+            .and_then(|s| s.values.get(block_id))
+            .and_then(|actions| actions.values().next())
+        else {
+            return Vec::new();
+        };
+        v.selected_options
+            .as_ref()
+            .map(|options| options.iter().map(|o| o.value.clone()).collect())
+            .or_else(|| v.selected_users.clone())
+            .or_else(|| v.selected_conversations.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// The outer payload of an Events API request.
+#[derive(Debug, Deserialize)]
+pub struct SlackEventEnvelope {
+    #[serde(rename = "type")]
+    pub envelope_type: String,
+    pub challenge: Option<String>,
+    pub event: Option<SlackEvent>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SlackEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub user: Option<String>,
+    pub tab: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SlackMessageRequest {
     pub channel: String,
     pub blocks: Vec<SlackBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_ts: Option<String>,
 }
 
-fn default_null_string() -> String {
-    "null".to_string()
+/// Treats `null`, a missing field and an empty or whitespace-only string alike.
+fn empty_as_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|s| !s.trim().is_empty()))
+}
+
+/// Accepts a visuals object, `null` or a bare placeholder string; an object with nothing in it
+/// counts as no visuals.
+fn visuals_or_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Visuals>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Object(Visuals),
+        #[allow(dead_code)]
+        Placeholder(String),
+    }
+
+    Ok(match Option::<Repr>::deserialize(deserializer)? {
+        Some(Repr::Object(visuals)) if !visuals.is_empty() => Some(visuals),
+        _ => None,
+    })
 } 
\ No newline at end of file
@@ -0,0 +1,277 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::{
+    bank::load_question_bank,
+    models::*,
+    slack::{mrkdwn_block, post_json_message, respond, respond_ephemeral, slack_api},
+    state::AppState,
+    store::{QuestionOverride, Report},
+    utils::{format_text_for_slack, generate_id, now_unix},
+};
+
+pub const REPORT_CALLBACK_ID: &str = "question_report";
+
+const REASONS: [(&str, &str); 4] = [
+    ("Broken formatting or LaTeX", "formatting"),
+    ("Missing figure", "missing_figure"),
+    ("Wrong answer key", "wrong_answer"),
+    ("Something else", "other"),
+];
+const ANSWER_LETTERS: [&str; 4] = ["A", "B", "C", "D"];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReportMetadata {
+    question_id: String,
+    channel_id: String,
+}
+
+/// Opens the "what's wrong?" modal from the ⚠️ Report menu on a question.
+pub async fn open_report_modal(state: &AppState, interaction: &SlackInteraction, action: &SlackAction) -> Result<()> {
+    let Some(trigger_id) = &interaction.trigger_id else {
+        return Ok(());
+    };
+    let Some(question_id) = action.selected_option.as_ref().map(|o| o.value.clone()) else {
+        return Ok(());
+    };
+    let token = state.config.slack.bot_token.clone();
+    let metadata = serde_json::to_string(&ReportMetadata {
+        question_id,
+        channel_id: interaction.channel.id.clone(),
+    })?;
+
+    let reasons: Vec<Value> = REASONS
+        .iter()
+        .map(|(label, value)| json!({ "text": { "type": "plain_text", "text": label }, "value": value }))
+        .collect();
+
+    slack_api(&token, "views.open", &json!({
+        "trigger_id": trigger_id,
+        "view": {
+            "type": "modal",
+            "callback_id": REPORT_CALLBACK_ID,
+            "private_metadata": metadata,
+            "title": { "type": "plain_text", "text": "Report question" },
+            "submit": { "type": "plain_text", "text": "Report" },
+            "close": { "type": "plain_text", "text": "Cancel" },
+            "blocks": [
+                {
+                    "type": "input",
+                    "block_id": "reason",
+                    "label": { "type": "plain_text", "text": "What's wrong?" },
+                    "element": {
+                        "type": "static_select",
+                        "action_id": "select",
+                        "options": reasons,
+                    }
+                },
+                {
+                    "type": "input",
+                    "block_id": "details",
+                    "optional": true,
+                    "label": { "type": "plain_text", "text": "Details" },
+                    "element": {
+                        "type": "plain_text_input",
+                        "action_id": "input",
+                        "multiline": true,
+                    }
+                }
+            ]
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Stores the report and forwards it to the moderator channel.
+pub async fn handle_report_submission(state: &AppState, interaction: &SlackInteraction) -> Result<()> {
+    let Some(view) = &interaction.view else {
+        return Ok(());
+    };
+    let metadata: ReportMetadata = serde_json::from_str(&view.private_metadata)?;
+
+    let report = Report {
+        id: generate_id(),
+        question_id: metadata.question_id,
+        user_id: interaction.user.id.clone(),
+        channel_id: metadata.channel_id,
+        reason: view.input_value("reason").unwrap_or_else(|| "other".to_string()),
+        details: view.input_value("details").unwrap_or_default(),
+        created_at: now_unix(),
+        status: "open".to_string(),
+        resolved_by: None,
+    };
+    state.store.update(|data| data.reports.push(report.clone())).await?;
+    tracing::info!("Question {} reported by {}: {}", report.question_id, report.user_id, report.reason);
+
+    let Some(moderator_channel) = state.config.moderation.channel.clone() else {
+        tracing::warn!("No moderator channel is configured; report {} was stored but not forwarded", report.id);
+        return Ok(());
+    };
+
+    let token = state.config.slack.bot_token.clone();
+    // Tutor-written questions can be reported too, so look in the same bank students answer from.
+    let bank = load_question_bank(state).await?;
+    let question = bank.iter().find(|q| q.id == report.question_id);
+
+    post_json_message(&token, &json!({
+        "channel": moderator_channel,
+        "text": format!("Question {} was reported", report.question_id),
+        "blocks": moderation_blocks(&report, question, None),
+    }))
+    .await?;
+    Ok(())
+}
+
+pub async fn handle_moderation_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    if !state.config.is_admin(&interaction.user.id) {
+        return respond_ephemeral(&interaction.response_url, "Only configured admins can moderate questions.").await;
+    }
+
+    // Buttons carry the report ID; the answer menu carries `report_id|letter`.
+    let value = action
+        .selected_option
+        .as_ref()
+        .map(|o| o.value.clone())
+        .or_else(|| action.value.clone())
+        .unwrap_or_default();
+    let (report_id, letter) = value.split_once('|').unwrap_or((value.as_str(), ""));
+    let moderator = interaction.user.id.clone();
+    let now = now_unix();
+
+    let outcome = state
+        .store
+        .update(|data| {
+            let report = data.reports.iter().find(|r| r.id == report_id)?.clone();
+            let status = match action.action_id.as_str() {
+                "mod_hide" => {
+                    let entry = data.question_overrides.entry(report.question_id.clone()).or_default();
+                    entry.hidden = true;
+                    touch(entry, &moderator, now);
+                    format!("🙈 Hidden from rotation by <@{}>", moderator)
+                }
+                "mod_restore" => {
+                    let entry = data.question_overrides.entry(report.question_id.clone()).or_default();
+                    entry.hidden = false;
+                    touch(entry, &moderator, now);
+                    format!("↩️ Restored to rotation by <@{}>", moderator)
+                }
+                "mod_override" if ANSWER_LETTERS.contains(&letter) => {
+                    let entry = data.question_overrides.entry(report.question_id.clone()).or_default();
+                    entry.correct_answer = Some(letter.to_string());
+                    touch(entry, &moderator, now);
+                    format!("✏️ Answer key set to *{}* by <@{}>", letter, moderator)
+                }
+                "mod_dismiss" => format!("🗑️ Dismissed by <@{}>", moderator),
+                _ => return None,
+            };
+
+            // Acting on a question settles every open report about it.
+            let resolution = if action.action_id == "mod_dismiss" { "dismissed" } else { "resolved" };
+            for other in data.reports.iter_mut().filter(|r| {
+                r.status == "open" && (r.id == report.id || (resolution == "resolved" && r.question_id == report.question_id))
+            }) {
+                other.status = resolution.to_string();
+                other.resolved_by = Some(moderator.clone());
+            }
+            Some((report, status))
+        })
+        .await?;
+
+    let Some((report, status)) = outcome else {
+        return Ok(());
+    };
+
+    let bank = load_question_bank(state).await.unwrap_or_default();
+    let question = bank.iter().find(|q| q.id == report.question_id);
+    respond(&interaction.response_url, &json!({
+        "replace_original": true,
+        "text": status,
+        "blocks": moderation_blocks(&report, question, Some(&status)),
+    }))
+    .await
+}
+
+fn touch(entry: &mut QuestionOverride, moderator: &str, now: u64) {
+    entry.updated_by = moderator.to_string();
+    entry.updated_at = now;
+}
+
+fn moderation_blocks(report: &Report, question: Option<&SATQuestion>, status: Option<&str>) -> Vec<Value> {
+    let reason = REASONS
+        .iter()
+        .find(|(_, value)| *value == report.reason)
+        .map(|(label, _)| *label)
+        .unwrap_or(&report.reason);
+
+    let mut blocks = vec![mrkdwn_block(&format!(
+        "*⚠️ Question `{}` reported* by <@{}>{}\n*Reason:* {}{}",
+        report.question_id,
+        report.user_id,
+        if report.channel_id.is_empty() { String::new() } else { format!(" in <#{}>", report.channel_id) },
+        reason,
+        if report.details.is_empty() { String::new() } else { format!("\n*Details:* {}", report.details) }
+    ))];
+
+    match question {
+        Some(q) => blocks.push(mrkdwn_block(&format!(
+            "*Question:* {}\nA. {}\nB. {}\nC. {}\nD. {}\n*Answer key:* {}",
+            format_text_for_slack(&q.question.question),
+            format_text_for_slack(&q.question.choices.a),
+            format_text_for_slack(&q.question.choices.b),
+            format_text_for_slack(&q.question.choices.c),
+            format_text_for_slack(&q.question.choices.d),
+            q.question.correct_answer
+        ))),
+        None => blocks.push(mrkdwn_block("_The question is hidden or no longer in the bank._")),
+    }
+
+    if let Some(status) = status {
+        blocks.push(mrkdwn_block(status));
+    }
+
+    let answer_options: Vec<Value> = ANSWER_LETTERS
+        .iter()
+        .map(|letter| {
+            json!({
+                "text": { "type": "plain_text", "text": format!("Set answer to {}", letter) },
+                "value": format!("{}|{}", report.id, letter),
+            })
+        })
+        .collect();
+
+    blocks.push(json!({
+        "type": "actions",
+        "elements": [
+            {
+                "type": "button",
+                "text": { "type": "plain_text", "text": "🙈 Hide question", "emoji": true },
+                "style": "danger",
+                "action_id": "mod_hide",
+                "value": report.id,
+            },
+            {
+                "type": "button",
+                "text": { "type": "plain_text", "text": "↩️ Restore", "emoji": true },
+                "action_id": "mod_restore",
+                "value": report.id,
+            },
+            {
+                "type": "button",
+                "text": { "type": "plain_text", "text": "Dismiss", "emoji": true },
+                "action_id": "mod_dismiss",
+                "value": report.id,
+            },
+            {
+                "type": "overflow",
+                "action_id": "mod_override",
+                "options": answer_options,
+            }
+        ]
+    }));
+    blocks
+}
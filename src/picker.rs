@@ -0,0 +1,200 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use crate::{
+    bank::load_question_bank,
+    discussion::post_channel_question,
+    history::{choose_preferring_unseen, record_served, recently_seen, EXHAUSTED_MESSAGE},
+    metrics::metrics,
+    models::*,
+    rating::register_question,
+    settings::{allows_question, channel_filter, channel_settings},
+    slack::{post_message, section_block, slack_api},
+    state::AppState,
+    utils::now_unix,
+};
+
+pub const PICKER_CALLBACK_ID: &str = "question_picker";
+
+const ANY: &str = "any";
+const SECTIONS: [(&str, &str); 1] = [("Math", "math")];
+const DIFFICULTIES: [&str; 3] = ["Easy", "Medium", "Hard"];
+const COUNTS: [usize; 4] = [1, 3, 5, 10];
+
+/// Carried through the modal so the submission knows where to post.
+#[derive(Debug, Serialize, Deserialize)]
+struct PickerMetadata {
+    channel_id: String,
+    /// The opening command's `response_url`, for channels the bot hasn't joined.
+    #[serde(default)]
+    response_url: Option<String>,
+}
+
+/// Opens a placeholder modal right away (trigger IDs expire after three seconds),
+/// then fills in the domain list once the question bank has loaded.
+pub async fn open_picker(
+    state: &AppState,
+    trigger_id: &str,
+    channel_id: &str,
+    response_url: Option<&str>,
+) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let metadata = serde_json::to_string(&PickerMetadata {
+        channel_id: channel_id.to_string(),
+        response_url: response_url.map(str::to_string),
+    })?;
+
+    let opened = slack_api(&token, "views.open", &json!({
+        "trigger_id": trigger_id,
+        "view": {
+            "type": "modal",
+            "callback_id": PICKER_CALLBACK_ID,
+            "private_metadata": metadata,
+            "title": { "type": "plain_text", "text": "Choose questions" },
+            "blocks": [{
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": "⏳ Loading the question bank..." }
+            }]
+        }
+    }))
+    .await?;
+    let view_id = opened["view"]["id"].as_str().unwrap_or_default().to_string();
+
+    let settings = channel_settings(state, Some(channel_id)).await;
+    let bank = load_question_bank(state).await?;
+    let domains: BTreeSet<String> = bank
+        .iter()
+        .filter(|q| allows_question(&settings, q))
+        .map(|q| q.domain.clone())
+        .collect();
+    let difficulties: Vec<&str> = DIFFICULTIES
+        .into_iter()
+        .filter(|d| settings.difficulties.is_empty() || settings.difficulties.iter().any(|s| s.eq_ignore_ascii_case(d)))
+        .collect();
+
+    slack_api(&token, "views.update", &json!({
+        "view_id": view_id,
+        "view": {
+            "type": "modal",
+            "callback_id": PICKER_CALLBACK_ID,
+            "private_metadata": metadata,
+            "title": { "type": "plain_text", "text": "Choose questions" },
+            "submit": { "type": "plain_text", "text": "Post" },
+            "close": { "type": "plain_text", "text": "Cancel" },
+            "blocks": picker_blocks(&domains, &difficulties),
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+pub async fn handle_picker_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    match (action.action_id.as_str(), interaction.trigger_id.as_deref()) {
+        ("picker_open", Some(trigger_id)) => {
+            open_picker(state, trigger_id, &interaction.channel.id, Some(&interaction.response_url)).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Posts the chosen questions to the channel the picker was opened from.
+pub async fn handle_picker_submission(state: &AppState, interaction: &SlackInteraction) -> Result<()> {
+    let Some(view) = &interaction.view else {
+        return Ok(());
+    };
+    let metadata: PickerMetadata = serde_json::from_str(&view.private_metadata)?;
+    let user_id = interaction.user.id.as_str();
+    let channel_id = metadata.channel_id.as_str();
+
+    let domain = view.input_value("domain").filter(|d| d != ANY);
+    let difficulty = view.input_value("difficulty").filter(|d| d != ANY);
+    let count: usize = view.input_value("count").and_then(|c| c.parse().ok()).unwrap_or(1);
+
+    let token = state.config.slack.bot_token.clone();
+    let allowed = channel_filter(state, Some(channel_id)).await;
+    let bank = load_question_bank(state).await?;
+    let pool: Vec<&SATQuestion> = bank
+        .iter()
+        .filter(|q| allowed(q))
+        .filter(|q| domain.as_deref().is_none_or(|d| q.domain == d))
+        .filter(|q| difficulty.as_deref().is_none_or(|d| q.difficulty.eq_ignore_ascii_case(d)))
+        .collect();
+
+    if pool.is_empty() {
+        post_message(&token, user_id, vec![section_block("No questions match those filters. Try widening them.")])
+            .await?;
+        return Ok(());
+    }
+
+    let now = now_unix();
+    let window = state.config.repeat_window_secs(Some(channel_id));
+    let seen = state
+        .store
+        .read(|data| recently_seen(data, Some(user_id), Some(channel_id), now, window))
+        .await;
+    let exhausted = pool.iter().all(|q| seen.contains(&q.id));
+    let questions = choose_preferring_unseen(pool, &seen, count);
+
+    let ids: Vec<&str> = questions.iter().map(|q| q.id.as_str()).collect();
+    state
+        .store
+        .update(|data| {
+            for question in &questions {
+                register_question(data, question);
+            }
+            record_served(data, Some(user_id), Some(channel_id), &ids, now, window);
+        })
+        .await?;
+
+    if exhausted {
+        post_message(&token, user_id, vec![section_block(EXHAUSTED_MESSAGE)]).await?;
+    }
+    for question in &questions {
+        post_channel_question(state, channel_id, metadata.response_url.as_deref(), None, question, false).await?;
+        metrics().question_posted("picker");
+    }
+    Ok(())
+}
+
+fn picker_blocks(domains: &BTreeSet<String>, difficulties: &[&str]) -> Vec<Value> {
+    let option = |label: &str, value: &str| {
+        json!({ "text": { "type": "plain_text", "text": label }, "value": value })
+    };
+
+    let sections: Vec<Value> = SECTIONS.iter().map(|(label, value)| option(label, value)).collect();
+    // Static selects take at most 100 options.
+    let domain_options: Vec<Value> = std::iter::once(option("Any domain", ANY))
+        .chain(domains.iter().take(99).map(|d| option(d, d)))
+        .collect();
+    let difficulty_options: Vec<Value> = std::iter::once(option("Any difficulty", ANY))
+        .chain(difficulties.iter().map(|d| option(d, d)))
+        .collect();
+    let count_options: Vec<Value> = COUNTS.iter().map(|c| option(&c.to_string(), &c.to_string())).collect();
+
+    vec![
+        select_input("section", "Section", sections),
+        select_input("domain", "Domain", domain_options),
+        select_input("difficulty", "Difficulty", difficulty_options),
+        select_input("count", "Number of questions", count_options),
+    ]
+}
+
+fn select_input(block_id: &str, label: &str, options: Vec<Value>) -> Value {
+    let initial = options.first().cloned().unwrap_or_default();
+    json!({
+        "type": "input",
+        "block_id": block_id,
+        "label": { "type": "plain_text", "text": label },
+        "element": {
+            "type": "static_select",
+            "action_id": "select",
+            "options": options,
+            "initial_option": initial,
+        }
+    })
+}
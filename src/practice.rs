@@ -0,0 +1,638 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+    time::Duration,
+};
+use crate::{
+    achievements::track_attempt,
+    bank::load_question_bank,
+    errors::report_failure,
+    history::{choose_preferring_unseen, record_served, recently_seen},
+    metrics::metrics,
+    models::*,
+    rating::record_attempt,
+    scheduler::{schedule_job, PRACTICE_DEADLINE_JOB},
+    settings::channel_filter,
+    slack::{
+        create_answer_buttons, create_question_content_blocks, create_report_overflow, mrkdwn_block,
+        post_json_message, respond, respond_ephemeral,
+    },
+    state::AppState,
+    streaks::track_correct_answer,
+    store::{Attempt, PracticeSession, ScheduledJob},
+    utils::{generate_id, now_unix},
+};
+
+/// A digital SAT math module: 22 questions in 35 minutes.
+pub const PRACTICE_QUESTION_COUNT: usize = 22;
+pub const PRACTICE_TIME_LIMIT: Duration = Duration::from_secs(35 * 60);
+
+/// How many questions of each difficulty make up a module, easiest first.
+const DIFFICULTY_MIX: [(&str, usize); 3] = [("Easy", 7), ("Medium", 8), ("Hard", 7)];
+
+impl PracticeSession {
+    fn remaining(&self) -> Duration {
+        Duration::from_secs((self.started_at + self.time_limit_secs).saturating_sub(now_unix()))
+    }
+
+    fn elapsed_secs(&self) -> u64 {
+        now_unix().saturating_sub(self.started_at).min(self.time_limit_secs)
+    }
+
+    fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// The next question after `index` that still needs attention, wrapping around once.
+    fn next_unanswered(&self, index: usize) -> Option<usize> {
+        let total = self.questions.len();
+        (1..=total)
+            .map(|offset| (index + offset) % total)
+            .find(|&i| self.answers[i].is_none())
+    }
+}
+
+/// Maps a raw score to a scaled section score. Points are interpolated linearly.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScoreTable {
+    pub points: Vec<ScorePoint>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ScorePoint {
+    pub raw: u32,
+    pub scaled: u32,
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        let points = [(0, 200), (3, 280), (6, 370), (9, 450), (12, 520), (15, 590), (18, 670), (20, 730), (22, 800)]
+            .into_iter()
+            .map(|(raw, scaled)| ScorePoint { raw, scaled })
+            .collect();
+        Self { points }
+    }
+}
+
+impl ScoreTable {
+    /// Reads the table from `questions.score_table_path` (a JSON `{"points": [...]}` file) if set.
+    pub fn load(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_str::<ScoreTable>(&contents)?))
+        {
+            Ok(mut table) if !table.points.is_empty() => {
+                table.points.sort_by_key(|p| p.raw);
+                table
+            }
+            Ok(_) => {
+                tracing::error!("Score table {} has no points, using the default", path.display());
+                Self::default()
+            }
+            Err(e) => {
+                tracing::error!("Failed to load score table {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Converts `correct` out of `total`, rescaling when the module was shorter than the table.
+    pub fn scale(&self, correct: usize, total: usize) -> u32 {
+        let max_raw = self.points.last().map(|p| p.raw).unwrap_or(0);
+        if total == 0 || max_raw == 0 {
+            return 200;
+        }
+        let raw = correct as f64 * max_raw as f64 / total as f64;
+
+        let scaled = match self.points.windows(2).find(|w| raw <= w[1].raw as f64) {
+            Some([low, high]) => {
+                let span = (high.raw - low.raw).max(1) as f64;
+                let t = (raw - low.raw as f64) / span;
+                low.scaled as f64 + t * (high.scaled as f64 - low.scaled as f64)
+            }
+            _ => self.points.last().map(|p| p.scaled as f64).unwrap_or(200.0),
+        };
+
+        // Section scores are reported in steps of 10.
+        ((scaled / 10.0).round() * 10.0).clamp(200.0, 800.0) as u32
+    }
+}
+
+pub async fn start_practice(state: &AppState, command: &SlackSlashCommand, args: &str) -> Value {
+    let section = args.split_whitespace().next().unwrap_or("math").to_lowercase();
+    if section != "math" {
+        return json!({
+            "response_type": "ephemeral",
+            "text": format!("Practice modules are only available for math right now (you asked for `{}`).", section)
+        });
+    }
+
+    if state.practice_sessions.lock().await.values().any(|s| s.user_id == command.user_id) {
+        return json!({
+            "response_type": "ephemeral",
+            "text": "You already have a practice module in progress — check your DMs."
+        });
+    }
+
+    let user_id = command.user_id.clone();
+    let channel_id = command.channel_id.clone();
+    let response_url = command.response_url.clone();
+    state.spawn(move |state| async move {
+        if let Err(e) = begin_session(&state, &user_id, &channel_id).await {
+            report_failure(&response_url, "starting your practice module", &e).await;
+        }
+    });
+
+    json!({
+        "response_type": "ephemeral",
+        "text": format!(
+            "📝 Starting a {}-question math module ({} minutes). Check your DMs!",
+            PRACTICE_QUESTION_COUNT,
+            PRACTICE_TIME_LIMIT.as_secs() / 60
+        )
+    })
+}
+
+/// Starts a module in the user's DMs from questions the channel it was started in allows.
+async fn begin_session(state: &AppState, user_id: &str, channel_id: &str) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let allowed = channel_filter(state, Some(channel_id)).await;
+    let bank: Vec<SATQuestion> = load_question_bank(state).await?.into_iter().filter(|q| allowed(q)).collect();
+    let now = now_unix();
+    let window = state.config.repeat_window_secs(None);
+    let seen = state.store.read(|data| recently_seen(data, Some(user_id), None, now, window)).await;
+    let questions = select_module_questions(&bank, &seen);
+    if questions.is_empty() {
+        return Err(anyhow::anyhow!("No questions available for a practice module"));
+    }
+    let ids: Vec<&str> = questions.iter().map(|q| q.id.as_str()).collect();
+    metrics().questions_posted.with_label_values(&["practice"]).inc_by(questions.len() as u64);
+    state.store.update(|data| record_served(data, Some(user_id), None, &ids, now, window)).await?;
+
+    let session = PracticeSession {
+        id: generate_id(),
+        user_id: user_id.to_string(),
+        answers: vec![None; questions.len()],
+        questions,
+        flagged: HashSet::new(),
+        started_at: now,
+        time_limit_secs: PRACTICE_TIME_LIMIT.as_secs(),
+    };
+    let session_id = session.id.clone();
+
+    let intro = json!({
+        "channel": user_id,
+        "text": "Practice module started",
+        "blocks": [mrkdwn_block(&format!(
+            "*📝 Math practice module*\n{} questions, {} minutes. Answers aren't revealed until you submit. \
+             Use *Skip* to come back later and *Flag* to mark a question for review.",
+            session.questions.len(),
+            session.time_limit_secs / 60
+        ))],
+    });
+    let first_question = json!({
+        "channel": user_id,
+        "text": "Practice question 1",
+        "blocks": practice_question_blocks(&session, 0),
+    });
+    save_session(state, &session).await?;
+    state.practice_sessions.lock().await.insert(session_id.clone(), session);
+
+    if let Err(e) = async {
+        post_json_message(&token, &intro).await?;
+        post_json_message(&token, &first_question).await
+    }
+    .await
+    {
+        state.practice_sessions.lock().await.remove(&session_id);
+        state.store.update(|data| data.practice_sessions.remove(&session_id)).await?;
+        return Err(e);
+    }
+
+    // Submit automatically when the time budget runs out. Both the deadline and the session are
+    // persisted, so the module is still scored if the bot restarts in between.
+    schedule_job(state, ScheduledJob {
+        id: generate_id(),
+        kind: PRACTICE_DEADLINE_JOB.to_string(),
+        target: session_id,
+        user_id: user_id.to_string(),
+        run_at: now + PRACTICE_TIME_LIMIT.as_secs(),
+        channel_id: None,
+        message_ts: None,
+        thread_ts: None,
+    })
+    .await
+}
+
+/// Loads the practice modules saved in the store, so their buttons and deadlines keep working
+/// after a restart. Runs at startup, before the scheduler resumes persisted jobs and before the
+/// server accepts requests.
+pub async fn restore_sessions(state: &AppState) {
+    let saved = state.store.read(|data| data.practice_sessions.clone()).await;
+    if !saved.is_empty() {
+        tracing::info!("Restoring {} practice module(s)", saved.len());
+    }
+    state.practice_sessions.lock().await.extend(saved);
+}
+
+async fn save_session(state: &AppState, session: &PracticeSession) -> Result<()> {
+    state
+        .store
+        .update(|data| {
+            data.practice_sessions.insert(session.id.clone(), session.clone());
+        })
+        .await
+}
+
+/// Runs a `practice_deadline` job. A session missing from the store predates sessions being
+/// saved, so only an apology is left to send.
+pub async fn expire_session(state: &AppState, job: &ScheduledJob) -> Result<()> {
+    if state.practice_sessions.lock().await.contains_key(&job.target) {
+        return finish_session(state, &job.target, true).await;
+    }
+    let token = state.config.slack.bot_token.clone();
+    post_json_message(&token, &json!({
+        "channel": job.user_id,
+        "text": "⚠️ Your practice module was interrupted by a bot restart and couldn't be scored. \
+                 Start a new one with `/sat practice`.",
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Draws the difficulty mix, preferring questions the user hasn't seen recently and topping up
+/// from the rest of the bank when a bucket runs short.
+fn select_module_questions(bank: &[SATQuestion], seen: &HashSet<String>) -> Vec<SATQuestion> {
+    let mut selected: Vec<SATQuestion> = Vec::new();
+
+    for (difficulty, count) in DIFFICULTY_MIX {
+        let pool = bank.iter().filter(|q| q.difficulty.eq_ignore_ascii_case(difficulty));
+        selected.extend(choose_preferring_unseen(pool, seen, count));
+    }
+
+    if selected.len() < PRACTICE_QUESTION_COUNT {
+        let used: HashSet<String> = selected.iter().map(|q| q.id.clone()).collect();
+        let rest = bank.iter().filter(|q| !used.contains(&q.id));
+        selected.extend(choose_preferring_unseen(rest, seen, PRACTICE_QUESTION_COUNT - selected.len()));
+    }
+
+    selected.sort_by_key(|q| difficulty_rank(&q.difficulty));
+    selected
+}
+
+fn difficulty_rank(difficulty: &str) -> usize {
+    DIFFICULTY_MIX
+        .iter()
+        .position(|(d, _)| d.eq_ignore_ascii_case(difficulty))
+        .unwrap_or(DIFFICULTY_MIX.len())
+}
+
+pub async fn handle_practice_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    let value = action.value.clone().unwrap_or_default();
+    let mut parts = value.splitn(3, ':');
+    let session_id = parts.next().unwrap_or_default().to_string();
+    let index: usize = parts.next().unwrap_or("0").parse().unwrap_or(0);
+    let choice = parts.next().unwrap_or_default().to_string();
+
+    let expired = {
+        let sessions = state.practice_sessions.lock().await;
+        match sessions.get(&session_id) {
+            // Stale or forged buttons can carry any index.
+            Some(session) if session.user_id == interaction.user.id && index >= session.questions.len() => {
+                drop(sessions);
+                return respond_ephemeral(&interaction.response_url, "That question isn't part of this module.").await;
+            }
+            Some(session) if session.user_id == interaction.user.id => session.is_expired(),
+            Some(_) => return Ok(()),
+            None => {
+                drop(sessions);
+                return respond_ephemeral(&interaction.response_url, "This practice module has already ended.").await;
+            }
+        }
+    };
+    if expired {
+        return finish_session(state, &session_id, true).await;
+    }
+
+    match action.action_id.as_str() {
+        "practice_finish" => return finish_session(state, &session_id, false).await,
+        "practice_goto" => return show_question(state, interaction, &session_id, index).await,
+        "practice_review" => return show_review(state, interaction, &session_id).await,
+        _ => {}
+    }
+
+    let (next, snapshot) = {
+        let mut sessions = state.practice_sessions.lock().await;
+        let Some(session) = sessions.get_mut(&session_id) else {
+            return Ok(());
+        };
+        let next = match action.action_id.as_str() {
+            "practice_flag" => {
+                if !session.flagged.remove(&index) {
+                    session.flagged.insert(index);
+                }
+                None
+            }
+            "practice_skip" => Some(session.next_unanswered(index)),
+            id if id.starts_with("practice_answer_") => {
+                let Some(answer) = session.answers.get_mut(index) else {
+                    return Ok(());
+                };
+                *answer = Some(choice);
+                Some(session.next_unanswered(index))
+            }
+            other => {
+                tracing::warn!("Unknown practice action: {}", other);
+                return Ok(());
+            }
+        };
+        (next, session.clone())
+    };
+    save_session(state, &snapshot).await?;
+
+    match next {
+        // Flag toggled: redraw the same question.
+        None => show_question(state, interaction, &session_id, index).await,
+        Some(Some(next_index)) if next_index > index => {
+            show_question(state, interaction, &session_id, next_index).await
+        }
+        // Reached the end of the module, or everything has been answered.
+        Some(_) => show_review(state, interaction, &session_id).await,
+    }
+}
+
+async fn show_question(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    session_id: &str,
+    index: usize,
+) -> Result<()> {
+    let blocks = {
+        let sessions = state.practice_sessions.lock().await;
+        let Some(session) = sessions.get(session_id) else {
+            return Ok(());
+        };
+        if index >= session.questions.len() {
+            return Ok(());
+        }
+        practice_question_blocks(session, index)
+    };
+
+    respond(&interaction.response_url, &json!({
+        "replace_original": true,
+        "text": format!("Practice question {}", index + 1),
+        "blocks": blocks,
+    }))
+    .await
+}
+
+async fn show_review(state: &AppState, interaction: &SlackInteraction, session_id: &str) -> Result<()> {
+    let blocks = {
+        let sessions = state.practice_sessions.lock().await;
+        let Some(session) = sessions.get(session_id) else {
+            return Ok(());
+        };
+        review_blocks(session)
+    };
+
+    respond(&interaction.response_url, &json!({
+        "replace_original": true,
+        "text": "Review your module",
+        "blocks": blocks,
+    }))
+    .await
+}
+
+async fn finish_session(state: &AppState, session_id: &str, timed_out: bool) -> Result<()> {
+    let Some(session) = state.practice_sessions.lock().await.remove(session_id) else {
+        return Ok(());
+    };
+    let token = state.config.slack.bot_token.clone();
+
+    let timestamp = now_unix();
+    state
+        .store
+        .update(|data| {
+            data.scheduled_jobs.retain(|job| job.target != session.id);
+            data.practice_sessions.remove(&session.id);
+            for (question, answer) in session.questions.iter().zip(&session.answers) {
+                let Some(selected) = answer else { continue };
+                record_attempt(data, Attempt {
+                    user_id: session.user_id.clone(),
+                    channel_id: String::new(),
+                    question_id: question.id.clone(),
+                    domain: question.domain.clone(),
+                    difficulty: question.difficulty.clone(),
+                    selected: selected.clone(),
+                    correct: *selected == question.question.correct_answer,
+                    timestamp,
+                    source: "practice".to_string(),
+                    message_ts: None,
+                    hints: 0,
+                });
+            }
+        })
+        .await?;
+    if session.questions.iter().zip(&session.answers).any(|(q, a)| a.as_ref() == Some(&q.question.correct_answer)) {
+        track_correct_answer(state, &session.user_id, None);
+    }
+    track_attempt(state, &session.user_id, None);
+
+    post_json_message(&token, &json!({
+        "channel": session.user_id,
+        "text": "Practice module results",
+        "blocks": results_blocks(&session, &state.score_table, timed_out),
+    }))
+    .await?;
+    Ok(())
+}
+
+fn practice_question_blocks(session: &PracticeSession, index: usize) -> Value {
+    let question = &session.questions[index];
+    let total = session.questions.len();
+    let remaining = session.remaining().as_secs();
+    let flag = if session.flagged.contains(&index) { " 🚩" } else { "" };
+    let answered = session
+        .answers
+        .get(index)
+        .and_then(Option::as_ref)
+        .map(|a| format!(" · your answer: *{}*", a))
+        .unwrap_or_default();
+
+    let mut blocks = vec![mrkdwn_block(&format!(
+        "*Question {} of {}*{} · ⏱ {}:{:02} left{}",
+        index + 1,
+        total,
+        flag,
+        remaining / 60,
+        remaining % 60,
+        answered
+    ))];
+    blocks.extend(
+        create_question_content_blocks(question)
+            .into_iter()
+            .map(|b| serde_json::to_value(b).unwrap_or_default()),
+    );
+    blocks.push(json!({
+        "type": "actions",
+        "elements": create_answer_buttons(question, "practice_answer", |letter| {
+            format!("{}:{}:{}", session.id, index, letter)
+        }),
+    }));
+    blocks.push(json!({
+        "type": "actions",
+        "elements": [
+            nav_button("⏭ Skip", "practice_skip", &session.id, index),
+            nav_button(if flag.is_empty() { "🚩 Flag" } else { "Unflag" }, "practice_flag", &session.id, index),
+            nav_button("📋 Review & submit", "practice_review", &session.id, index),
+            create_report_overflow(question),
+        ]
+    }));
+    Value::Array(blocks)
+}
+
+fn review_blocks(session: &PracticeSession) -> Value {
+    let unanswered: Vec<usize> = (0..session.questions.len()).filter(|&i| session.answers[i].is_none()).collect();
+    let mut flagged: Vec<usize> = session.flagged.iter().copied().collect();
+    flagged.sort_unstable();
+
+    let describe = |items: &[usize]| {
+        if items.is_empty() {
+            "none".to_string()
+        } else {
+            items.iter().map(|i| (i + 1).to_string()).collect::<Vec<_>>().join(", ")
+        }
+    };
+
+    let mut blocks = vec![mrkdwn_block(&format!(
+        "*📋 Review*\nAnswered: {} of {}\nUnanswered: {}\nFlagged: {}",
+        session.questions.len() - unanswered.len(),
+        session.questions.len(),
+        describe(&unanswered),
+        describe(&flagged)
+    ))];
+
+    let mut revisit: Vec<usize> = unanswered.iter().chain(flagged.iter()).copied().collect();
+    revisit.sort_unstable();
+    revisit.dedup();
+    // Slack allows at most 25 elements per actions block.
+    for chunk in revisit.chunks(24) {
+        let buttons: Vec<Value> = chunk
+            .iter()
+            .map(|&i| nav_button(&format!("Q{}", i + 1), "practice_goto", &session.id, i))
+            .collect();
+        blocks.push(json!({ "type": "actions", "elements": buttons }));
+    }
+
+    blocks.push(json!({
+        "type": "actions",
+        "elements": [{
+            "type": "button",
+            "text": { "type": "plain_text", "text": "✅ Submit module", "emoji": true },
+            "style": "primary",
+            "action_id": "practice_finish",
+            "value": format!("{}:0", session.id),
+        }]
+    }));
+    Value::Array(blocks)
+}
+
+fn results_blocks(session: &PracticeSession, table: &ScoreTable, timed_out: bool) -> Value {
+    let total = session.questions.len();
+    let mut by_domain: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut correct = 0;
+
+    for (question, answer) in session.questions.iter().zip(&session.answers) {
+        let entry = by_domain.entry(question.domain.as_str()).or_default();
+        entry.1 += 1;
+        if answer.as_deref() == Some(question.question.correct_answer.as_str()) {
+            entry.0 += 1;
+            correct += 1;
+        }
+    }
+
+    let breakdown = by_domain
+        .iter()
+        .map(|(domain, (right, count))| format!("• {}: {}/{}", domain, right, count))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let missed = session
+        .questions
+        .iter()
+        .zip(&session.answers)
+        .enumerate()
+        .filter(|(_, (q, a))| a.as_deref() != Some(q.question.correct_answer.as_str()))
+        .map(|(i, (q, a))| format!("Q{} ({}→{})", i + 1, a.as_deref().unwrap_or("–"), q.question.correct_answer))
+        .collect::<Vec<_>>();
+
+    let elapsed = session.elapsed_secs();
+    let mut blocks = vec![mrkdwn_block(&format!(
+        "*🏁 Module complete{}*\nRaw score: *{}/{}*\nEstimated section score: *{}* (200–800)\nTime used: {}:{:02}",
+        if timed_out { " — time's up" } else { "" },
+        correct,
+        total,
+        table.scale(correct, total),
+        elapsed / 60,
+        elapsed % 60
+    ))];
+    blocks.push(mrkdwn_block(&format!("*By domain*\n{}", breakdown)));
+    if !missed.is_empty() {
+        blocks.push(mrkdwn_block(&format!("*Missed*\n{}", missed.join(", "))));
+    }
+    Value::Array(blocks)
+}
+
+fn nav_button(label: &str, action_id: &str, session_id: &str, index: usize) -> Value {
+    json!({
+        "type": "button",
+        "text": { "type": "plain_text", "text": label, "emoji": true },
+        "action_id": action_id,
+        "value": format!("{}:{}", session_id, index),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_hits_table_points() {
+        let table = ScoreTable::default();
+        assert_eq!(table.scale(0, 22), 200);
+        assert_eq!(table.scale(12, 22), 520);
+        assert_eq!(table.scale(22, 22), 800);
+    }
+
+    #[test]
+    fn scale_interpolates_and_rounds_to_tens() {
+        let table = ScoreTable::default();
+        // Halfway between (3, 280) and (6, 370).
+        assert_eq!(table.scale(9, 44), 330);
+        assert_eq!(table.scale(1, 22), 230);
+    }
+
+    #[test]
+    fn scale_rescales_short_modules() {
+        let table = ScoreTable::default();
+        assert_eq!(table.scale(11, 11), 800);
+        assert_eq!(table.scale(6, 11), table.scale(12, 22));
+    }
+
+    #[test]
+    fn scale_handles_empty_input() {
+        assert_eq!(ScoreTable::default().scale(0, 0), 200);
+        assert_eq!(ScoreTable { points: Vec::new() }.scale(5, 10), 200);
+    }
+}
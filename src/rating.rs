@@ -0,0 +1,210 @@
+use rand::prelude::*;
+use std::collections::HashSet;
+use crate::{
+    hints::hint_multiplier,
+    metrics::metrics,
+    models::SATQuestion,
+    review::schedule_review,
+    store::{Attempt, QuestionStats, StoreData, UserRating},
+};
+
+pub const DEFAULT_RATING: f64 = 1500.0;
+
+/// New players move fast and settle down as they answer more, Glicko-style.
+const MAX_USER_K: f64 = 64.0;
+const MIN_USER_K: f64 = 16.0;
+/// Questions are answered by many people, so each answer nudges them less.
+const QUESTION_K: f64 = 8.0;
+/// How many of the closest-rated questions to choose from, so adaptive play isn't deterministic.
+const ADAPTIVE_CANDIDATES: usize = 5;
+
+pub fn initial_question_rating(difficulty: &str) -> f64 {
+    match difficulty.to_lowercase().as_str() {
+        "easy" => 1300.0,
+        "hard" => 1700.0,
+        _ => DEFAULT_RATING,
+    }
+}
+
+/// Probability that a player rated `player` answers a question rated `question` correctly.
+pub fn expected_score(player: f64, question: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((question - player) / 400.0))
+}
+
+fn user_k(games: u32) -> f64 {
+    (MAX_USER_K / (1.0 + games as f64 / 10.0)).max(MIN_USER_K)
+}
+
+/// Remembers a question's domain and difficulty so later answers can be rated without the bank.
+pub fn register_question(data: &mut StoreData, question: &SATQuestion) {
+    data.questions
+        .entry(question.id.clone())
+        .or_insert_with(|| QuestionStats {
+            domain: question.domain.clone(),
+            difficulty: question.difficulty.clone(),
+            rating: initial_question_rating(&question.difficulty),
+            attempts: 0,
+            correct: 0,
+        });
+}
+
+pub fn user_rating(data: &StoreData, user_id: &str, domain: &str) -> UserRating {
+    data.user_ratings
+        .get(user_id)
+        .and_then(|domains| domains.get(domain))
+        .cloned()
+        .unwrap_or(UserRating { rating: DEFAULT_RATING, games: 0 })
+}
+
+pub fn question_rating(data: &StoreData, question: &SATQuestion) -> f64 {
+    data.questions
+        .get(&question.id)
+        .map(|q| q.rating)
+        .unwrap_or_else(|| initial_question_rating(&question.difficulty))
+}
+
+/// Stores the attempt and, unless it is a retry on the same message, updates both ratings
+/// and the user's review queue.
+/// Returns the user's rating change for the attempt's domain.
+pub fn record_attempt(data: &mut StoreData, attempt: Attempt) -> f64 {
+    metrics()
+        .answers
+        .with_label_values(&[attempt.source.as_str(), if attempt.correct { "true" } else { "false" }])
+        .inc();
+    let is_retry = attempt.message_ts.is_some()
+        && data.attempts.iter().any(|a| {
+            a.user_id == attempt.user_id
+                && a.question_id == attempt.question_id
+                && a.message_ts == attempt.message_ts
+        });
+
+    let mut delta = 0.0;
+    if !is_retry {
+        let question = data
+            .questions
+            .entry(attempt.question_id.clone())
+            .or_insert_with(|| QuestionStats {
+                domain: attempt.domain.clone(),
+                difficulty: attempt.difficulty.clone(),
+                rating: initial_question_rating(&attempt.difficulty),
+                attempts: 0,
+                correct: 0,
+            });
+        let user = data
+            .user_ratings
+            .entry(attempt.user_id.clone())
+            .or_default()
+            .entry(attempt.domain.clone())
+            .or_insert(UserRating { rating: DEFAULT_RATING, games: 0 });
+
+        let score = if attempt.correct { 1.0 } else { 0.0 };
+        let expected = expected_score(user.rating, question.rating);
+
+        // Hints shrink what a correct answer is worth; a miss costs the same either way.
+        let surprise = if attempt.correct { (score - expected) * hint_multiplier(attempt.hints) } else { score - expected };
+        delta = user_k(user.games) * surprise;
+        user.rating += delta;
+        user.games += 1;
+
+        question.rating -= QUESTION_K * surprise;
+        question.attempts += 1;
+        if attempt.correct {
+            question.correct += 1;
+        }
+
+        schedule_review(data, &attempt);
+    }
+
+    data.attempts.push(attempt);
+    delta
+}
+
+/// The attempts [`record_attempt`] rated, oldest first: the first answer per user on each posted
+/// message counts and retries on it don't, so clicking the right button again earns nothing.
+pub fn rated_attempts(attempts: &[Attempt]) -> impl Iterator<Item = &Attempt> {
+    let mut seen = HashSet::new();
+    attempts.iter().filter(move |a| match &a.message_ts {
+        Some(ts) => seen.insert((a.user_id.as_str(), a.question_id.as_str(), ts.as_str())),
+        None => true,
+    })
+}
+
+/// Picks a question rated close to the user's level in that question's domain.
+pub fn pick_adaptive<'a>(
+    data: &StoreData,
+    pool: impl IntoIterator<Item = &'a SATQuestion>,
+    user_id: &str,
+    domain: Option<&str>,
+) -> Option<&'a SATQuestion> {
+    let mut candidates: Vec<(&SATQuestion, f64)> = pool
+        .into_iter()
+        .filter(|q| domain.is_none_or(|d| q.domain.eq_ignore_ascii_case(d)))
+        .map(|q| {
+            let player = user_rating(data, user_id, &q.domain).rating;
+            (q, (question_rating(data, q) - player).abs())
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    candidates.truncate(ADAPTIVE_CANDIDATES);
+    candidates.choose(&mut rand::thread_rng()).map(|(q, _)| *q)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::approx_eq;
+
+    #[test]
+    fn expected_score_is_even_at_equal_ratings() {
+        assert!(approx_eq(expected_score(1500.0, 1500.0), 0.5));
+        assert!(approx_eq(expected_score(1900.0, 1500.0), 1.0 / 1.1));
+        assert!(approx_eq(expected_score(1500.0, 1700.0) + expected_score(1700.0, 1500.0), 1.0));
+    }
+
+    #[test]
+    fn correct_answer_moves_user_up_and_question_down() {
+        let mut data = StoreData::default();
+        let delta = record_attempt(&mut data, Attempt::test("U1", "q1").correct(true));
+        assert!(approx_eq(delta, 32.0));
+        assert!(approx_eq(user_rating(&data, "U1", "Algebra").rating, 1532.0));
+        assert!(approx_eq(data.questions["q1"].rating, 1496.0));
+        assert_eq!(data.questions["q1"].correct, 1);
+    }
+
+    #[test]
+    fn miss_moves_user_down_and_question_up() {
+        let mut data = StoreData::default();
+        assert!(approx_eq(record_attempt(&mut data, Attempt::test("U1", "q1")), -32.0));
+        assert!(approx_eq(data.questions["q1"].rating, 1504.0));
+    }
+
+    #[test]
+    fn retry_on_the_same_message_is_stored_but_not_rated() {
+        let mut data = StoreData::default();
+        record_attempt(&mut data, Attempt::test("U1", "q1").on_message("1.1"));
+        let rating = user_rating(&data, "U1", "Algebra");
+        assert!(approx_eq(record_attempt(&mut data, Attempt::test("U1", "q1").correct(true).on_message("1.1")), 0.0));
+        assert!(approx_eq(user_rating(&data, "U1", "Algebra").rating, rating.rating));
+        assert_eq!(user_rating(&data, "U1", "Algebra").games, 1);
+        assert_eq!(data.attempts.len(), 2);
+    }
+
+    #[test]
+    fn hints_shrink_the_gain_from_a_correct_answer() {
+        assert!(approx_eq(hint_multiplier(0), 1.0));
+        assert!(approx_eq(hint_multiplier(2), 0.5));
+        assert!(approx_eq(hint_multiplier(9), hint_multiplier(crate::hints::MAX_HINTS)));
+
+        let mut data = StoreData::default();
+        let delta = record_attempt(&mut data, Attempt::test("U1", "q1").correct(true).hints(2));
+        assert!(approx_eq(delta, 16.0));
+        assert!(approx_eq(data.questions["q1"].rating, 1498.0));
+    }
+
+    #[test]
+    fn miss_costs_the_same_with_or_without_hints() {
+        let mut data = StoreData::default();
+        assert!(approx_eq(record_attempt(&mut data, Attempt::test("U1", "q1").hints(3)), -32.0));
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use crate::{
+    bank::load_question_bank,
+    errors::report_failure,
+    metrics::metrics,
+    models::*,
+    rating::register_question,
+    slack::{create_question_blocks, post_message, respond, section_block},
+    state::AppState,
+    store::{Attempt, ReviewItem, StoreData},
+    utils::now_unix,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+const DEFAULT_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+/// Applies an SM-2 update for a graded attempt. A miss puts the question in the queue;
+/// later attempts on queued questions move it along.
+pub fn schedule_review(data: &mut StoreData, attempt: &Attempt) {
+    let queue = data.reviews.entry(attempt.user_id.clone()).or_default();
+    if !attempt.correct && !queue.contains_key(&attempt.question_id) {
+        queue.insert(attempt.question_id.clone(), ReviewItem {
+            question_id: attempt.question_id.clone(),
+            ease: DEFAULT_EASE,
+            interval_days: 0,
+            repetitions: 0,
+            due: attempt.timestamp,
+        });
+    }
+
+    let Some(item) = queue.get_mut(&attempt.question_id) else {
+        return;
+    };
+
+    // Binary grading maps onto SM-2 quality 4 (correct after thought) or 1 (wrong).
+    let quality: f64 = if attempt.correct { 4.0 } else { 1.0 };
+    if quality >= 3.0 {
+        item.interval_days = match item.repetitions {
+            0 => 1,
+            1 => 6,
+            _ => (item.interval_days as f64 * item.ease).round() as u32,
+        };
+        item.repetitions += 1;
+    } else {
+        item.repetitions = 0;
+        item.interval_days = 1;
+    }
+    item.ease = (item.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)).max(MIN_EASE);
+    item.due = attempt.timestamp + item.interval_days as u64 * DAY;
+}
+
+pub fn due_items(data: &StoreData, user_id: &str, now: u64) -> Vec<ReviewItem> {
+    let mut due: Vec<ReviewItem> = data
+        .reviews
+        .get(user_id)
+        .map(|queue| queue.values().filter(|item| item.due <= now).cloned().collect())
+        .unwrap_or_default();
+    due.sort_by_key(|item| item.due);
+    due
+}
+
+fn next_due(data: &StoreData, user_id: &str) -> Option<u64> {
+    data.reviews.get(user_id)?.values().map(|item| item.due).min()
+}
+
+pub async fn start_review(state: &AppState, command: &SlackSlashCommand, args: &str) -> Value {
+    let mut words = args.split_whitespace();
+    if words.next().is_some_and(|w| w.eq_ignore_ascii_case("remind")) {
+        let enable = !words.next().is_some_and(|w| w.eq_ignore_ascii_case("off"));
+        let user_id = command.user_id.clone();
+        let result = state
+            .store
+            .update(|data| {
+                if enable {
+                    data.review_reminders.entry(user_id).or_insert(None);
+                } else {
+                    data.review_reminders.remove(&user_id);
+                }
+            })
+            .await;
+        let text = match (result, enable) {
+            (Err(e), _) => {
+                tracing::error!("Failed to update review reminder: {}", e);
+                "Sorry, I couldn't save that setting.".to_string()
+            }
+            (Ok(()), true) => "🔔 I'll DM you once a day when you have questions due for review.".to_string(),
+            (Ok(()), false) => "🔕 Daily review reminders turned off.".to_string(),
+        };
+        return json!({ "response_type": "ephemeral", "text": text });
+    }
+
+    let user_id = command.user_id.clone();
+    let response_url = command.response_url.clone();
+    state.spawn(move |state| async move {
+        if let Err(e) = send_next_review(&state, &user_id, &response_url).await {
+            report_failure(&response_url, "loading your review queue", &e).await;
+        }
+    });
+
+    json!({
+        "response_type": "ephemeral",
+        "text": "📚 Loading your review queue..."
+    })
+}
+
+pub async fn handle_review_action(
+    state: &AppState,
+    interaction: &SlackInteraction,
+    action: &SlackAction,
+) -> Result<()> {
+    match action.action_id.as_str() {
+        "review_next" => send_next_review(state, &interaction.user.id, &interaction.response_url).await,
+        other => {
+            tracing::warn!("Unknown review action: {}", other);
+            Ok(())
+        }
+    }
+}
+
+async fn send_next_review(state: &AppState, user_id: &str, response_url: &str) -> Result<()> {
+    let now = now_unix();
+    let (due, next) = state
+        .store
+        .read(|data| (due_items(data, user_id, now), next_due(data, user_id)))
+        .await;
+
+    if due.is_empty() {
+        let text = match next {
+            Some(next) => format!(
+                "🎉 Nothing due for review right now. Your next review is in {}.",
+                describe_wait(next.saturating_sub(now))
+            ),
+            None => "🎉 Your review queue is empty. Questions you miss will show up here.".to_string(),
+        };
+        return respond(response_url, &json!({
+            "response_type": "ephemeral",
+            "replace_original": true,
+            "text": text,
+        }))
+        .await;
+    }
+
+    let bank = load_question_bank(state).await?;
+    let Some((question, remaining)) = due
+        .iter()
+        .enumerate()
+        .find_map(|(i, item)| bank.iter().find(|q| q.id == item.question_id).map(|q| (q.clone(), due.len() - i)))
+    else {
+        return respond(response_url, &json!({
+            "response_type": "ephemeral",
+            "replace_original": true,
+            "text": "The questions due for review are no longer in the question bank.",
+        }))
+        .await;
+    };
+
+    state.store.update(|data| register_question(data, &question)).await?;
+
+    let mut blocks: Vec<Value> = vec![serde_json::to_value(section_block(&format!(
+        "📚 *Review* — {} question{} due",
+        remaining,
+        if remaining == 1 { "" } else { "s" }
+    )))?];
+    blocks.extend(
+        create_question_blocks(&question, state.config.features.hints)
+            .into_iter()
+            .map(|b| serde_json::to_value(b).unwrap_or_default()),
+    );
+    blocks.push(json!({
+        "type": "actions",
+        "elements": [{
+            "type": "button",
+            "text": { "type": "plain_text", "text": "Next review ▶", "emoji": true },
+            "action_id": "review_next",
+            "value": "next"
+        }]
+    }));
+
+    respond(response_url, &json!({
+        "response_type": "ephemeral",
+        "replace_original": true,
+        "text": "Review question",
+        "blocks": blocks,
+    }))
+    .await?;
+    metrics().question_posted("review");
+    Ok(())
+}
+
+/// Sends the daily "questions due" DM to opted-in users once the reminder hour has passed.
+pub async fn send_review_reminders(state: &AppState) -> Result<()> {
+    let now = now_unix();
+    let today = now / DAY;
+    if (now % DAY) / 3600 < state.config.review.reminder_hour {
+        return Ok(());
+    }
+
+    let pending: Vec<(String, usize)> = state
+        .store
+        .read(|data| {
+            data.review_reminders
+                .iter()
+                .filter(|(_, last)| **last != Some(today))
+                .map(|(user, _)| (user.clone(), due_items(data, user, now).len()))
+                .collect()
+        })
+        .await;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let token = state.config.slack.bot_token.clone();
+    for (user_id, count) in &pending {
+        if *count == 0 {
+            continue;
+        }
+        let text = format!(
+            "📚 You have {} question{} due for review. Run `/sat review` to work through them.",
+            count,
+            if *count == 1 { "" } else { "s" }
+        );
+        if let Err(e) = post_message(&token, user_id, vec![section_block(&text)]).await {
+            tracing::error!("Failed to send review reminder to {}: {}", user_id, e);
+        }
+    }
+
+    state
+        .store
+        .update(|data| {
+            for (user_id, _) in &pending {
+                if let Some(last) = data.review_reminders.get_mut(user_id) {
+                    *last = Some(today);
+                }
+            }
+        })
+        .await?;
+    Ok(())
+}
+
+fn describe_wait(seconds: u64) -> String {
+    match seconds {
+        s if s < 3600 => format!("{} minutes", (s / 60).max(1)),
+        s if s < DAY => format!("{} hours", s / 3600),
+        s => format!("{} days", s / DAY),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(correct: bool, timestamp: u64) -> Attempt {
+        Attempt::test("U1", "q1").correct(correct).at(timestamp)
+    }
+
+    fn item(data: &StoreData) -> &ReviewItem {
+        &data.reviews["U1"]["q1"]
+    }
+
+    #[test]
+    fn correct_answer_to_unqueued_question_is_not_queued() {
+        let mut data = StoreData::default();
+        schedule_review(&mut data, &answer(true, 0));
+        assert!(data.reviews["U1"].is_empty());
+    }
+
+    #[test]
+    fn miss_queues_question_for_tomorrow() {
+        let mut data = StoreData::default();
+        schedule_review(&mut data, &answer(false, 1000));
+        let item = item(&data);
+        assert_eq!(item.interval_days, 1);
+        assert_eq!(item.repetitions, 0);
+        assert_eq!(item.due, 1000 + DAY);
+        assert!((item.ease - 1.96).abs() < 1e-9);
+    }
+
+    #[test]
+    fn correct_reviews_follow_sm2_intervals() {
+        let mut data = StoreData::default();
+        schedule_review(&mut data, &answer(false, 0));
+        let intervals: Vec<u32> = (1..=4)
+            .map(|day| {
+                schedule_review(&mut data, &answer(true, day * DAY));
+                item(&data).interval_days
+            })
+            .collect();
+        // Ease stays at 1.96 because quality 4 leaves it unchanged.
+        assert_eq!(intervals, [1, 6, 12, 24]);
+        assert_eq!(item(&data).repetitions, 4);
+        assert_eq!(item(&data).due, 4 * DAY + 24 * DAY);
+    }
+
+    #[test]
+    fn miss_resets_progress_and_ease_has_a_floor() {
+        let mut data = StoreData::default();
+        schedule_review(&mut data, &answer(false, 0));
+        schedule_review(&mut data, &answer(true, DAY));
+        schedule_review(&mut data, &answer(true, 2 * DAY));
+        for day in 3..10 {
+            schedule_review(&mut data, &answer(false, day * DAY));
+        }
+        let item = item(&data);
+        assert_eq!(item.repetitions, 0);
+        assert_eq!(item.interval_days, 1);
+        assert_eq!(item.ease, MIN_EASE);
+    }
+
+    #[test]
+    fn due_items_are_oldest_first() {
+        let mut data = StoreData::default();
+        for (id, due) in [("q1", 300), ("q2", 100), ("q3", 900)] {
+            data.reviews.entry("U1".to_string()).or_default().insert(id.to_string(), ReviewItem {
+                question_id: id.to_string(),
+                ease: DEFAULT_EASE,
+                interval_days: 1,
+                repetitions: 0,
+                due,
+            });
+        }
+        let due: Vec<String> = due_items(&data, "U1", 500).into_iter().map(|i| i.question_id).collect();
+        assert_eq!(due, ["q2", "q1"]);
+    }
+}
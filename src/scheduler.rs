@@ -0,0 +1,110 @@
+use anyhow::Result;
+use std::time::Duration;
+use crate::{
+    competitions::run_competitions,
+    digest::send_digests,
+    discussion::close_question,
+    duel::expire_duels,
+    practice::expire_session,
+    review::send_review_reminders,
+    state::AppState,
+    store::ScheduledJob,
+    streaks::send_streak_reminders,
+    utils::now_unix,
+};
+
+const TICK: Duration = Duration::from_secs(60);
+
+/// Job kind that auto-submits a practice module when its time runs out.
+pub const PRACTICE_DEADLINE_JOB: &str = "practice_deadline";
+/// Job kind that posts a channel question's explanation in its thread once answering closes.
+pub const QUESTION_CLOSE_JOB: &str = "question_close";
+
+/// Runs periodic jobs (reminders, digests) once a minute until shutdown, and resumes any
+/// persisted jobs left over from the last run.
+pub fn spawn_scheduler(state: &AppState) {
+    state.spawn(|state| async move {
+        let pending = state.store.read(|data| data.scheduled_jobs.clone()).await;
+        if !pending.is_empty() {
+            tracing::info!("Resuming {} scheduled job(s)", pending.len());
+        }
+        for job in pending {
+            spawn_job(&state, job);
+        }
+
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            tokio::select! {
+                _ = state.shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            expire_duels(&state).await;
+            if let Err(e) = send_review_reminders(&state).await {
+                tracing::error!("Failed to send review reminders: {}", e);
+            }
+            if state.config.features.streaks {
+                if let Err(e) = send_streak_reminders(&state).await {
+                    tracing::error!("Failed to process streaks: {}", e);
+                }
+            }
+            if state.config.features.competitions {
+                if let Err(e) = run_competitions(&state).await {
+                    tracing::error!("Failed to post competition standings: {}", e);
+                }
+            }
+            if state.config.features.digests {
+                if let Err(e) = send_digests(&state).await {
+                    tracing::error!("Failed to send weekly digests: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Persists `job` and arms a timer for it.
+pub async fn schedule_job(state: &AppState, job: ScheduledJob) -> Result<()> {
+    state.store.update(|data| data.scheduled_jobs.push(job.clone())).await?;
+    spawn_job(state, job);
+    Ok(())
+}
+
+/// Waits until the job is due, then runs and forgets it. Shutdown abandons the wait and leaves
+/// the job in the store for the next start.
+fn spawn_job(state: &AppState, job: ScheduledJob) {
+    state.spawn(move |state| async move {
+        let delay = Duration::from_secs(job.run_at.saturating_sub(now_unix()));
+        tokio::select! {
+            _ = state.shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        let still_pending = state
+            .store
+            .update(|data| {
+                let before = data.scheduled_jobs.len();
+                data.scheduled_jobs.retain(|j| j.id != job.id);
+                data.scheduled_jobs.len() != before
+            })
+            .await;
+        match still_pending {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                tracing::error!("Failed to claim scheduled job {}: {}", job.id, e);
+                return;
+            }
+        }
+
+        let result = match job.kind.as_str() {
+            PRACTICE_DEADLINE_JOB => expire_session(&state, &job).await,
+            QUESTION_CLOSE_JOB => close_question(&state, &job).await,
+            other => {
+                tracing::warn!("Unknown scheduled job kind: {}", other);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            tracing::error!("Scheduled job {} ({}) failed: {}", job.id, job.kind, e);
+        }
+    });
+}
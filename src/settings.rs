@@ -0,0 +1,340 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use crate::{
+    bank::load_question_bank,
+    models::*,
+    slack::{post_message, section_block, slack_api},
+    state::AppState,
+    store::ChannelSettings,
+    utils::now_unix,
+};
+
+pub const SETTINGS_CALLBACK_ID: &str = "channel_settings";
+
+const SECTIONS: [(&str, &str); 1] = [("Math", "math")];
+const DIFFICULTIES: [&str; 3] = ["Easy", "Medium", "Hard"];
+const ATTEMPT_POLICIES: [(&str, &str); 2] = [("Unlimited retries", "unlimited"), ("One answer per person", "single")];
+const REVEAL_POLICIES: [(&str, &str); 3] = [
+    ("Never", "never"),
+    ("After a correct answer", "after_correct"),
+    ("After any answer", "after_answer"),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsMetadata {
+    channel_id: String,
+}
+
+/// The channel's saved settings, or ones derived from the config's channel defaults.
+pub async fn channel_settings(state: &AppState, channel_id: Option<&str>) -> ChannelSettings {
+    let saved = match channel_id {
+        Some(channel_id) => state.store.read(|data| data.channel_settings.get(channel_id).cloned()).await,
+        None => None,
+    };
+    saved.unwrap_or_else(|| {
+        let defaults = state.config.channel_defaults(channel_id);
+        ChannelSettings {
+            domains: defaults.domain.into_iter().collect(),
+            difficulties: defaults.difficulty.into_iter().collect(),
+            ..ChannelSettings::default()
+        }
+    })
+}
+
+/// Whether the channel's settings let this question be served there.
+pub fn allows_question(settings: &ChannelSettings, question: &SATQuestion) -> bool {
+    // Every question in the bank is math for now.
+    let section_ok = settings.sections.is_empty() || settings.sections.iter().any(|s| s == "math");
+    let domain_ok = settings.domains.is_empty() || settings.domains.iter().any(|d| d.eq_ignore_ascii_case(&question.domain));
+    let difficulty_ok = settings.difficulties.is_empty()
+        || settings.difficulties.iter().any(|d| d.eq_ignore_ascii_case(&question.difficulty));
+    section_ok && domain_ok && difficulty_ok
+}
+
+/// Configured admins, the channel's creator and workspace admins and owners may change its settings.
+///
+/// Channel managers can't be checked: Slack only lists them through admin and SCIM APIs that need
+/// an Enterprise Grid org token, and neither `conversations.info` nor `users.info` says who manages
+/// a channel for a bot token. A channel manager who should configure the bot goes in `admins`.
+///
+/// DMs and group DMs have no creator. When the bot can't read the channel or user (no
+/// `channels:read`/`groups:read`/`users:read`) those checks fail closed.
+async fn can_configure(state: &AppState, channel_id: &str, user_id: &str) -> bool {
+    if state.config.is_admin(user_id) {
+        return true;
+    }
+    let token = &state.config.slack.bot_token;
+    match slack_api(token, "conversations.info", &json!({ "channel": channel_id })).await {
+        Ok(info) if info["channel"]["creator"].as_str() == Some(user_id) => return true,
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Couldn't look up the creator of {}: {}", channel_id, e),
+    }
+    match slack_api(token, "users.info", &json!({ "user": user_id })).await {
+        Ok(info) => info["user"]["is_admin"].as_bool() == Some(true) || info["user"]["is_owner"].as_bool() == Some(true),
+        Err(e) => {
+            tracing::warn!("Couldn't look up whether {} is a workspace admin: {}", user_id, e);
+            false
+        }
+    }
+}
+
+/// `/sat config`: opens the settings modal for the current channel.
+pub async fn open_settings_modal(state: &AppState, command: &SlackSlashCommand) -> Result<()> {
+    let token = state.config.slack.bot_token.clone();
+    let metadata = serde_json::to_string(&SettingsMetadata {
+        channel_id: command.channel_id.clone(),
+    })?;
+
+    // Open first: the trigger ID expires after three seconds.
+    let opened = slack_api(&token, "views.open", &json!({
+        "trigger_id": command.trigger_id,
+        "view": {
+            "type": "modal",
+            "callback_id": SETTINGS_CALLBACK_ID,
+            "private_metadata": metadata,
+            "title": { "type": "plain_text", "text": "Channel settings" },
+            "blocks": [{
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": "⏳ Loading settings..." }
+            }]
+        }
+    }))
+    .await?;
+    let view_id = opened["view"]["id"].as_str().unwrap_or_default().to_string();
+
+    if !can_configure(state, &command.channel_id, &command.user_id).await {
+        slack_api(&token, "views.update", &json!({
+            "view_id": view_id,
+            "view": {
+                "type": "modal",
+                "title": { "type": "plain_text", "text": "Channel settings" },
+                "close": { "type": "plain_text", "text": "Close" },
+                "blocks": [{
+                    "type": "section",
+                    "text": {
+                        "type": "mrkdwn",
+                        "text": "🔒 Only the channel's creator or a configured admin can change its SAT settings."
+                    }
+                }]
+            }
+        }))
+        .await?;
+        return Ok(());
+    }
+
+    let settings = channel_settings(state, Some(&command.channel_id)).await;
+    let bank = load_question_bank(state).await?;
+    let domains: BTreeSet<String> = bank.iter().map(|q| q.domain.clone()).collect();
+
+    slack_api(&token, "views.update", &json!({
+        "view_id": view_id,
+        "view": {
+            "type": "modal",
+            "callback_id": SETTINGS_CALLBACK_ID,
+            "private_metadata": metadata,
+            "title": { "type": "plain_text", "text": "Channel settings" },
+            "submit": { "type": "plain_text", "text": "Save" },
+            "close": { "type": "plain_text", "text": "Cancel" },
+            "blocks": settings_blocks(&settings, &domains),
+        }
+    }))
+    .await?;
+    Ok(())
+}
+
+pub async fn handle_settings_submission(state: &AppState, interaction: &SlackInteraction) -> Result<()> {
+    let Some(view) = &interaction.view else {
+        return Ok(());
+    };
+    let metadata: SettingsMetadata = serde_json::from_str(&view.private_metadata)?;
+    let user_id = interaction.user.id.clone();
+    if !can_configure(state, &metadata.channel_id, &user_id).await {
+        return Ok(());
+    }
+
+    let settings = ChannelSettings {
+        sections: view.input_values("sections"),
+        domains: view.input_values("domains"),
+        difficulties: view.input_values("difficulties"),
+        attempt_policy: view.input_value("attempt_policy").unwrap_or_else(|| "unlimited".to_string()),
+        reveal_policy: view.input_value("reveal_policy").unwrap_or_else(|| "never".to_string()),
+        answer_deadline_minutes: view.input_value("deadline").and_then(|m| m.trim().parse().ok()).filter(|m| *m > 0),
+        leaderboard: !view.input_values("leaderboard").is_empty(),
+        updated_by: user_id.clone(),
+        updated_at: now_unix(),
+    };
+    let summary = describe(&settings);
+    state
+        .store
+        .update(|data| data.channel_settings.insert(metadata.channel_id.clone(), settings))
+        .await?;
+
+    let token = state.config.slack.bot_token.clone();
+    let text = format!("⚙️ <@{}> updated the SAT settings for this channel:\n{}", user_id, summary);
+    if let Err(e) = post_message(&token, &metadata.channel_id, vec![section_block(&text)]).await {
+        // The bot may not be a member of the channel; fall back to telling the user directly.
+        tracing::warn!("Failed to announce settings in {}: {}", metadata.channel_id, e);
+        post_message(&token, &user_id, vec![section_block(&text)]).await?;
+    }
+    Ok(())
+}
+
+/// Tells the user why an answer wasn't accepted, if the channel's settings reject it.
+pub async fn answer_rejection(
+    state: &AppState,
+    settings: &ChannelSettings,
+    interaction: &SlackInteraction,
+) -> Option<String> {
+    let message_ts = interaction.message_ts();
+
+    if let (Some(minutes), Some(posted)) = (
+        settings.answer_deadline_minutes,
+        message_ts.as_deref().and_then(|ts| ts.split('.').next()?.parse::<u64>().ok()),
+    ) {
+        if now_unix() > posted + minutes * 60 {
+            return Some(format!("⏰ Answers for this question closed after {} minutes.", minutes));
+        }
+    }
+
+    if settings.attempt_policy == "single" {
+        let user_id = &interaction.user.id;
+        let answered = state
+            .store
+            .read(|data| {
+                data.attempts
+                    .iter()
+                    .any(|a| a.user_id == *user_id && a.source == "channel" && a.message_ts == message_ts)
+            })
+            .await;
+        if answered && message_ts.is_some() {
+            return Some("☝️ This channel allows one answer per question, and you've already answered.".to_string());
+        }
+    }
+    None
+}
+
+fn describe(settings: &ChannelSettings) -> String {
+    let list = |values: &[String]| if values.is_empty() { "all".to_string() } else { values.join(", ") };
+    let label = |options: &[(&'static str, &'static str)], value: &str| {
+        options.iter().find(|(_, v)| *v == value).map(|(l, _)| *l).unwrap_or("unknown")
+    };
+    format!(
+        "• Sections: {}\n• Domains: {}\n• Difficulties: {}\n• Attempts: {}\n• Explanations: {}\n• Answer deadline: {}\n• Leaderboard: {}",
+        list(&settings.sections),
+        list(&settings.domains),
+        list(&settings.difficulties),
+        label(&ATTEMPT_POLICIES, &settings.attempt_policy),
+        label(&REVEAL_POLICIES, &settings.reveal_policy),
+        settings
+            .answer_deadline_minutes
+            .map(|m| format!("{} minutes", m))
+            .unwrap_or_else(|| "none".to_string()),
+        if settings.leaderboard { "on" } else { "off" },
+    )
+}
+
+fn settings_blocks(settings: &ChannelSettings, domains: &BTreeSet<String>) -> Vec<Value> {
+    let option = |label: &str, value: &str| json!({ "text": { "type": "plain_text", "text": label }, "value": value });
+    let selected = |options: &[Value], values: &[String]| -> Vec<Value> {
+        options
+            .iter()
+            .filter(|o| values.iter().any(|v| o["value"].as_str() == Some(v)))
+            .cloned()
+            .collect()
+    };
+
+    let section_options: Vec<Value> = SECTIONS.iter().map(|(l, v)| option(l, v)).collect();
+    // Static selects take at most 100 options.
+    let domain_options: Vec<Value> = domains.iter().take(100).map(|d| option(d, d)).collect();
+    let difficulty_options: Vec<Value> = DIFFICULTIES.iter().map(|d| option(d, d)).collect();
+    let attempt_options: Vec<Value> = ATTEMPT_POLICIES.iter().map(|(l, v)| option(l, v)).collect();
+    let reveal_options: Vec<Value> = REVEAL_POLICIES.iter().map(|(l, v)| option(l, v)).collect();
+    let leaderboard_option = option("Show ratings and leaderboards in this channel", "on");
+
+    let mut blocks = vec![
+        multi_select("sections", "Sections (empty means all)", &section_options, selected(&section_options, &settings.sections)),
+        multi_select("domains", "Domains (empty means all)", &domain_options, selected(&domain_options, &settings.domains)),
+        multi_select(
+            "difficulties",
+            "Difficulties (empty means all)",
+            &difficulty_options,
+            selected(&difficulty_options, &settings.difficulties),
+        ),
+    ];
+
+    let initial_attempt = selected(&attempt_options, std::slice::from_ref(&settings.attempt_policy));
+    let initial_reveal = selected(&reveal_options, std::slice::from_ref(&settings.reveal_policy));
+    blocks.push(with_initial(
+        json!({
+            "type": "input",
+            "block_id": "attempt_policy",
+            "label": { "type": "plain_text", "text": "Attempts" },
+            "element": { "type": "radio_buttons", "action_id": "select", "options": attempt_options }
+        }),
+        initial_attempt.first(),
+    ));
+    blocks.push(with_initial(
+        json!({
+            "type": "input",
+            "block_id": "reveal_policy",
+            "label": { "type": "plain_text", "text": "Show the explanation" },
+            "element": { "type": "static_select", "action_id": "select", "options": reveal_options }
+        }),
+        initial_reveal.first(),
+    ));
+
+    let mut deadline = json!({
+        "type": "input",
+        "block_id": "deadline",
+        "optional": true,
+        "label": { "type": "plain_text", "text": "Answer deadline (minutes, empty for none)" },
+        "element": { "type": "number_input", "action_id": "input", "is_decimal_allowed": false, "min_value": "1" }
+    });
+    if let Some(minutes) = settings.answer_deadline_minutes {
+        deadline["element"]["initial_value"] = json!(minutes.to_string());
+    }
+    blocks.push(deadline);
+
+    let mut leaderboard = json!({
+        "type": "input",
+        "block_id": "leaderboard",
+        "optional": true,
+        "label": { "type": "plain_text", "text": "Leaderboard" },
+        "element": { "type": "checkboxes", "action_id": "select", "options": [leaderboard_option.clone()] }
+    });
+    if settings.leaderboard {
+        leaderboard["element"]["initial_options"] = json!([leaderboard_option]);
+    }
+    blocks.push(leaderboard);
+    blocks
+}
+
+fn multi_select(block_id: &str, label: &str, options: &[Value], initial: Vec<Value>) -> Value {
+    let mut block = json!({
+        "type": "input",
+        "block_id": block_id,
+        "optional": true,
+        "label": { "type": "plain_text", "text": label },
+        "element": { "type": "multi_static_select", "action_id": "select", "options": options }
+    });
+    // Slack rejects an empty `initial_options` array.
+    if !initial.is_empty() {
+        block["element"]["initial_options"] = json!(initial);
+    }
+    block
+}
+
+fn with_initial(mut block: Value, initial: Option<&Value>) -> Value {
+    if let Some(initial) = initial {
+        block["element"]["initial_option"] = initial.clone();
+    }
+    block
+}
+
+/// Convenience for command paths that only need to filter the bank.
+pub async fn channel_filter(state: &AppState, channel_id: Option<&str>) -> impl Fn(&SATQuestion) -> bool {
+    let settings = channel_settings(state, channel_id).await;
+    move |question| allows_question(&settings, question)
+}
@@ -1,62 +1,190 @@
-use anyhow::Result;
-use crate::{models::*, utils::format_text_for_slack};
-use rand::prelude::*;
+use anyhow::{Context, Result};
+use crate::{
+    config::{is_url, Config, TimeoutConfig},
+    errors::{BankParseError, SlackApiError, UpstreamError},
+    metrics::metrics,
+    models::*,
+    utils::format_text_for_slack,
+};
 use reqwest;
-use std::env;
+use serde_json::Value;
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
-pub async fn fetch_question() -> Result<SATQuestion> {
+pub const QUESTION_SOURCE_URL: &str = "https://api.jsonsilo.com/public/942c3c3b-3a0c-4be3-81c2-12029def19f5";
+
+/// Loads every configured question source in order and concatenates them.
+pub async fn fetch_question_bank(config: &Config) -> Result<Vec<SATQuestion>> {
+    let mut bank = Vec::new();
+    for source in &config.questions.sources {
+        let questions = if is_url(source) {
+            fetch_question_source(config, source).await?
+        } else {
+            let text = tokio::fs::read_to_string(source).await.map_err(|e| UpstreamError {
+                source: source.clone(),
+                reason: e.to_string(),
+            })?;
+            parse_question_bank(&text)?
+        };
+        bank.extend(questions);
+    }
+    Ok(bank)
+}
+
+async fn fetch_question_source(config: &Config, url: &str) -> Result<Vec<SATQuestion>> {
+    let started = Instant::now();
+    let result = fetch_question_source_inner(config, url).await;
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    metrics()
+        .upstream_fetch_seconds
+        .with_label_values(&[url, outcome])
+        .observe(started.elapsed().as_secs_f64());
+    result
+}
+
+async fn fetch_question_source_inner(config: &Config, url: &str) -> Result<Vec<SATQuestion>> {
     let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
+        .timeout(config.http_timeout())
         .build()?;
     
+    let unavailable = |reason: String| UpstreamError { source: url.to_string(), reason };
     let response = client
-        .get("https://api.jsonsilo.com/public/942c3c3b-3a0c-4be3-81c2-12029def19f5")
+        .get(url)
         .send()
-        .await?;
+        .await
+        .map_err(|e| unavailable(e.to_string()))?;
 
     let status = response.status();
-    let response_text = response.text().await?;
+    let response_text = response.text().await.map_err(|e| unavailable(e.to_string()))?;
     tracing::debug!("API Response status: {}", status);
     tracing::debug!("API Response body: {}", response_text);
 
     if !status.is_success() {
-        return Err(anyhow::anyhow!(
-            "API request failed with status {}: {}",
-            status,
-            response_text
-        ));
+        return Err(unavailable(format!("status {}: {}", status, response_text)).into());
     }
 
-    match serde_json::from_str::<MathResponse>(&response_text) {
-        Ok(data) => {
-            let mut rng = rand::thread_rng();
-            let question = data.math.choose(&mut rng)
-                .ok_or_else(|| anyhow::anyhow!("No questions available in the response"))?;
-            
-            tracing::debug!("Successfully parsed question: {:?}", question);
-            Ok(question.clone())
-        },
+    parse_question_bank(&response_text)
+}
+
+/// Parses an upstream payload: normally `{"math": [...]}`, occasionally a single bare question.
+pub fn parse_question_bank(response_text: &str) -> Result<Vec<SATQuestion>> {
+    match serde_json::from_str::<MathResponse>(response_text) {
+        Ok(data) => Ok(data.math),
         Err(e) => {
             tracing::error!("Failed to parse response as MathResponse: {}", e);
             tracing::error!("Response text: {}", response_text);
             
-            match serde_json::from_str::<SATQuestion>(&response_text) {
+            match serde_json::from_str::<SATQuestion>(response_text) {
                 Ok(question) => {
                     tracing::debug!("Successfully parsed single question: {:?}", question);
-                    Ok(question)
+                    Ok(vec![question])
                 },
                 Err(e2) => {
                     tracing::error!("Also failed to parse as single question: {}", e2);
-                    Err(anyhow::anyhow!("Failed to parse API response: {}", e))
+                    Err(BankParseError(e.to_string()).into())
                 }
             }
         }
     }
 }
 
-pub fn create_question_blocks(question: &SATQuestion) -> Vec<SlackBlock> {
+/// `with_hint` adds the "💡 Hint" button, which needs a `response_url` to answer on.
+pub fn create_question_blocks(question: &SATQuestion, with_hint: bool) -> Vec<SlackBlock> {
+    question_blocks(question, true, with_hint)
+}
+
+/// The question without its passage, for channel posts that put the passage in the thread.
+pub fn create_compact_question_blocks(question: &SATQuestion, with_hint: bool) -> Vec<SlackBlock> {
+    question_blocks(question, false, with_hint)
+}
+
+fn question_blocks(question: &SATQuestion, with_passage: bool, with_hint: bool) -> Vec<SlackBlock> {
     tracing::debug!("Creating blocks for question: {:?}", question);
     
+    let correct_answer = &question.question.correct_answer;
+    let mut blocks = create_question_content_blocks(question);
+    if !with_passage {
+        blocks.truncate(1);
+    }
+
+    blocks.push(SlackBlock {
+        block_type: "actions".to_string(),
+        text: None,
+        elements: Some(create_answer_buttons(question, "answer", |letter| {
+            format!("{}:{}:{}", letter, correct_answer, question.id)
+        })),
+        accessory: None,
+    });
+
+    let mut controls = Vec::new();
+    if with_hint {
+        controls.push(SlackElement {
+            element_type: "button".to_string(),
+            text: Some(SlackText {
+                text_type: "plain_text".to_string(),
+                text: "💡 Hint".to_string(),
+                emoji: Some(true),
+            }),
+            action_id: "hint_question".to_string(),
+            value: Some(question.id.clone()),
+            options: None,
+        });
+    }
+    controls.extend([SlackElement {
+            element_type: "button".to_string(),
+            text: Some(SlackText {
+                text_type: "plain_text".to_string(),
+                text: "🗑️ Clear".to_string(),
+                emoji: Some(true),
+            }),
+            action_id: "clear_message".to_string(),
+            value: Some("clear".to_string()),
+            options: None,
+        }, SlackElement {
+            element_type: "button".to_string(),
+            text: Some(SlackText {
+                text_type: "plain_text".to_string(),
+                text: "🎛️ Choose…".to_string(),
+                emoji: Some(true),
+            }),
+            action_id: "picker_open".to_string(),
+            value: Some("open".to_string()),
+            options: None,
+        }, create_report_overflow(question)]);
+
+    blocks.push(SlackBlock {
+        block_type: "actions".to_string(),
+        text: None,
+        elements: Some(controls),
+        accessory: None,
+    });
+
+    tracing::debug!("Generated blocks: {:?}", blocks);
+    blocks
+}
+
+/// The "⚠️ Report" menu attached to every posted question.
+pub fn create_report_overflow(question: &SATQuestion) -> SlackElement {
+    SlackElement {
+        element_type: "overflow".to_string(),
+        text: None,
+        action_id: "report_question".to_string(),
+        value: None,
+        options: Some(vec![SlackOption {
+            text: Some(SlackText {
+                text_type: "plain_text".to_string(),
+                text: "⚠️ Report".to_string(),
+                emoji: Some(true),
+            }),
+            value: question.id.clone(),
+        }]),
+    }
+}
+
+/// The question text and, when present, its paragraph, without any buttons.
+pub fn create_question_content_blocks(question: &SATQuestion) -> Vec<SlackBlock> {
     let mut blocks = vec![
         SlackBlock {
             block_type: "section".to_string(),
@@ -75,12 +203,12 @@ pub fn create_question_blocks(question: &SATQuestion) -> Vec<SlackBlock> {
         },
     ];
 
-    if question.question.paragraph != "null" {
+    if let Some(paragraph) = &question.question.paragraph {
         blocks.push(SlackBlock {
             block_type: "section".to_string(),
             text: Some(SlackText {
                 text_type: "mrkdwn".to_string(),
-                text: format!("*Paragraph:*\n{}", format_text_for_slack(&question.question.paragraph)),
+                text: format!("*Paragraph:*\n{}", format_text_for_slack(paragraph)),
                 emoji: None,
             }),
             elements: None,
@@ -88,84 +216,238 @@ pub fn create_question_blocks(question: &SATQuestion) -> Vec<SlackBlock> {
         });
     }
 
-    let choices = vec![
+    blocks
+}
+
+/// One button per choice; `action_prefix` and `value` decide which handler grades the click.
+pub fn create_answer_buttons(
+    question: &SATQuestion,
+    action_prefix: &str,
+    value: impl Fn(&str) -> String,
+) -> Vec<SlackElement> {
+    let choices = [
         ("A", &question.question.choices.a),
         ("B", &question.question.choices.b),
         ("C", &question.question.choices.c),
         ("D", &question.question.choices.d),
     ];
 
-    let correct_answer = &question.question.correct_answer;
-    let buttons = choices
+    choices
         .iter()
         .map(|(letter, text)| SlackElement {
             element_type: "button".to_string(),
-            text: SlackText {
+            text: Some(SlackText {
                 text_type: "plain_text".to_string(),
                 text: format!("{}. {}", letter, format_text_for_slack(text)),
                 emoji: Some(true),
-            },
-            action_id: format!("answer_{}", letter.to_lowercase()),
-            value: Some(format!("{}:{}", letter, correct_answer)),
+            }),
+            action_id: format!("{}_{}", action_prefix, letter.to_lowercase()),
+            value: Some(value(letter)),
+            options: None,
         })
-        .collect();
+        .collect()
+}
 
-    blocks.push(SlackBlock {
-        block_type: "actions".to_string(),
-        text: None,
-        elements: Some(buttons),
-        accessory: None,
-    });
+/// Client for Slack Web API and response_url calls. Global for the same reason as
+/// [`metrics()`]: the helpers take a token, not app state.
+static SLACK_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
 
-    blocks.push(SlackBlock {
-        block_type: "actions".to_string(),
-        text: None,
-        elements: Some(vec![SlackElement {
-            element_type: "button".to_string(),
-            text: SlackText {
-                text_type: "plain_text".to_string(),
-                text: "🗑️ Clear".to_string(),
-                emoji: Some(true),
-            },
-            action_id: "clear_message".to_string(),
-            value: Some("clear".to_string()),
-        }]),
-        accessory: None,
-    });
+/// Applies `timeouts.http_secs` to Slack calls. Called once from `AppState::new`; later calls
+/// keep the first timeout.
+pub fn init_slack_client(timeout: Duration) {
+    if let Ok(client) = reqwest::Client::builder().timeout(timeout).build() {
+        let _ = SLACK_CLIENT.set(client);
+    }
+}
 
-    tracing::debug!("Generated blocks: {:?}", blocks);
-    blocks
+pub fn slack_client() -> &'static reqwest::Client {
+    SLACK_CLIENT.get_or_init(|| {
+        let timeout = Duration::from_secs(TimeoutConfig::default().http_secs);
+        reqwest::Client::builder().timeout(timeout).build().unwrap_or_default()
+    })
 }
 
-pub async fn post_message(token: &str, channel: &str, blocks: Vec<SlackBlock>) -> Result<()> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+pub async fn slack_api(token: &str, method: &str, body: &Value) -> Result<Value> {
+    let client = slack_client();
+
+    tracing::debug!("Calling Slack API {}: {}", method, body);
+
+    let response = client
+        .post(format!("https://slack.com/api/{}", method))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(body)
+        .send()
+        .await
+        .inspect_err(|_| {
+            metrics().slack_api_errors.with_label_values(&[method, "http"]).inc();
+        })?;
+
+    let response_json: Value = response.json().await?;
+    tracing::debug!("Slack API response: {}", response_json);
+
+    if response_json["ok"] != Value::Bool(true) {
+        tracing::error!("Slack API error from {}: {}", method, response_json);
+        let error = response_json["error"].as_str().unwrap_or("unknown_error");
+        metrics().slack_api_errors.with_label_values(&[method, error]).inc();
+        return Err(SlackApiError {
+            method: method.to_string(),
+            code: error.to_string(),
+            needed: response_json["needed"].as_str().map(str::to_string),
+        }
+        .into());
+    }
+
+    Ok(response_json)
+}
+
+/// Calls a paginated method until `response_metadata.next_cursor` runs out and collects the
+/// `field` array from every page.
+pub async fn slack_api_all(token: &str, method: &str, body: &Value, field: &str) -> Result<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut body = body.clone();
+    loop {
+        let mut page = slack_api(token, method, &body).await?;
+        if let Some(page_items) = page[field].as_array_mut() {
+            items.append(page_items);
+        }
+        match page["response_metadata"]["next_cursor"].as_str() {
+            Some(cursor) if !cursor.is_empty() => body["cursor"] = Value::String(cursor.to_string()),
+            _ => return Ok(items),
+        }
+    }
+}
+
+/// Posts blocks to a channel (or a user ID for a DM) and returns the message `ts`.
+pub async fn post_message(token: &str, channel: &str, blocks: Vec<SlackBlock>) -> Result<String> {
+    post_in_thread(token, channel, None, blocks).await
+}
+
+/// Like [`post_message`], replying in the thread under `thread_ts` when there is one.
+pub async fn post_in_thread(
+    token: &str,
+    channel: &str,
+    thread_ts: Option<&str>,
+    blocks: Vec<SlackBlock>,
+) -> Result<String> {
     let message = SlackMessageRequest {
         channel: channel.to_string(),
         blocks,
+        thread_ts: thread_ts.map(str::to_string),
     };
 
     tracing::debug!("Sending message to Slack: {:?}", message);
 
-    let response = client
-        .post("https://slack.com/api/chat.postMessage")
-        .header("Authorization", format!("Bearer {}", token))
-        .json(&message)
-        .send()
-        .await?;
+    let response = slack_api(token, "chat.postMessage", &serde_json::to_value(&message)?)
+        .await
+        .context("Failed to post message")?;
 
-    let response_text = response.text().await?;
-    tracing::debug!("Slack API response: {}", response_text);
+    tracing::info!("Successfully posted message to Slack with response: {}", response);
+    Ok(response["ts"].as_str().unwrap_or_default().to_string())
+}
+
+pub async fn post_json_message(token: &str, message: &Value) -> Result<String> {
+    let response = slack_api(token, "chat.postMessage", message).await?;
+    Ok(response["ts"].as_str().unwrap_or_default().to_string())
+}
+
+/// How a reply to a slash command should reach its channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delivery {
+    /// Post `in_channel` through the command's `response_url`. Works in channels the bot hasn't
+    /// joined; falls back to `chat.postMessage` if the URL is spent or expired.
+    ResponseUrl,
+    /// Post with `chat.postMessage` because follow-ups (threads, edits, close-time explanations)
+    /// need the message `ts`. Falls back to a `response_url` reply when the bot isn't in the
+    /// channel, in which case there is no `ts`.
+    Message,
+}
 
-    if !response_text.contains("\"ok\":true") {
-        tracing::error!("Slack API error: {}", response_text);
-        return Err(anyhow::anyhow!(
-            "Failed to post message: {}",
-            response_text
-        ));
+/// Posts `message` (`text` and `blocks`) to the command's channel. Returns the message `ts` when it
+/// went out through `chat.postMessage`, and `None` when it went through `response_url`.
+pub async fn deliver(
+    token: &str,
+    channel_id: &str,
+    response_url: Option<&str>,
+    message: &Value,
+    delivery: Delivery,
+) -> Result<Option<String>> {
+    let mut posted = message.clone();
+    posted["channel"] = Value::from(channel_id);
+    let mut in_channel = message.clone();
+    in_channel["response_type"] = Value::from("in_channel");
+    in_channel["replace_original"] = Value::from(false);
+
+    match (delivery, response_url.filter(|url| !url.is_empty())) {
+        (Delivery::ResponseUrl, Some(url)) => match respond(url, &in_channel).await {
+            Ok(()) => Ok(None),
+            Err(e) => {
+                tracing::warn!("response_url reply failed, posting to {} instead: {}", channel_id, e);
+                post_json_message(token, &posted).await.map(Some)
+            }
+        },
+        (Delivery::Message, Some(url)) => match post_json_message(token, &posted).await {
+            Ok(ts) => Ok(Some(ts)),
+            Err(e) if is_membership_error(&e) => {
+                tracing::info!("Not a member of {}, replying through response_url", channel_id);
+                respond(url, &in_channel).await?;
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        },
+        (_, None) => post_json_message(token, &posted).await.map(Some),
     }
+}
 
-    tracing::info!("Successfully posted message to Slack with response: {}", response_text);
+/// `chat.postMessage` errors that a `response_url` reply gets around.
+pub fn is_membership_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<SlackApiError>())
+        .any(|e| matches!(e.code.as_str(), "not_in_channel" | "channel_not_found"))
+}
+
+pub async fn update_message(token: &str, channel: &str, ts: &str, message: &Value) -> Result<()> {
+    let mut body = message.clone();
+    body["channel"] = Value::String(channel.to_string());
+    body["ts"] = Value::String(ts.to_string());
+    slack_api(token, "chat.update", &body).await?;
     Ok(())
-} 
\ No newline at end of file
+}
+
+pub async fn respond(response_url: &str, message: &Value) -> Result<()> {
+    slack_client()
+        .post(response_url)
+        .json(message)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+pub async fn respond_ephemeral(response_url: &str, text: &str) -> Result<()> {
+    respond(response_url, &serde_json::json!({
+        "response_type": "ephemeral",
+        "replace_original": false,
+        "text": text,
+    }))
+    .await
+}
+
+pub fn section_block(text: &str) -> SlackBlock {
+    SlackBlock {
+        block_type: "section".to_string(),
+        text: Some(SlackText {
+            text_type: "mrkdwn".to_string(),
+            text: text.to_string(),
+            emoji: None,
+        }),
+        elements: None,
+        accessory: None,
+    }
+}
+
+pub fn mrkdwn_block(text: &str) -> Value {
+    serde_json::json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": text }
+    })
+}
@@ -0,0 +1,53 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{atomic::AtomicBool, Arc},
+};
+use tokio::sync::Mutex;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use crate::{authoring::AuthorDraft, config::Config, duel::Duel, practice::ScoreTable, slack::init_slack_client, store::{PracticeSession, Store}};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub store: Store,
+    pub duels: Arc<Mutex<HashMap<String, Duel>>>,
+    pub practice_sessions: Arc<Mutex<HashMap<String, PracticeSession>>>,
+    /// Loaded once at startup from `questions.score_table_path`.
+    pub score_table: Arc<ScoreTable>,
+    /// Authoring drafts by the preview's `private_metadata`.
+    pub author_drafts: Arc<Mutex<HashMap<String, AuthorDraft>>>,
+    /// Set once the question bank has loaded; `/readyz` waits on it.
+    pub bank_loaded: Arc<AtomicBool>,
+    /// Background work started by requests. Shutdown waits for it to drain.
+    pub tasks: TaskTracker,
+    /// Cancelled when the server starts shutting down, so long-running loops can stop early.
+    pub shutdown: CancellationToken,
+}
+
+impl AppState {
+    pub fn new(config: Config, store: Store) -> Self {
+        init_slack_client(config.http_timeout());
+        let score_table = Arc::new(ScoreTable::load(config.questions.score_table_path.as_deref()));
+        Self {
+            config: Arc::new(config),
+            score_table,
+            store,
+            duels: Arc::default(),
+            practice_sessions: Arc::default(),
+            author_drafts: Arc::default(),
+            bank_loaded: Arc::default(),
+            tasks: TaskTracker::new(),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    /// Runs `task` in the background on the tracker, handing it its own copy of the state.
+    pub fn spawn<F, Fut>(&self, task: F)
+    where
+        F: FnOnce(AppState) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task(self.clone()));
+    }
+}
@@ -0,0 +1,125 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use crate::{
+    achievements::achievement_lines,
+    models::SlackSlashCommand,
+    rating::user_rating,
+    review::due_items,
+    store::{Attempt, StoreData},
+    state::AppState,
+    streaks::user_streak,
+    utils::now_unix,
+};
+
+const RECENT_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct DomainSummary {
+    pub domain: String,
+    pub attempts: usize,
+    pub correct: usize,
+    pub rating: f64,
+}
+
+impl DomainSummary {
+    pub fn accuracy(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.correct as f64 / self.attempts as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserSummary {
+    pub attempts: usize,
+    pub correct: usize,
+    pub streak_days: u32,
+    pub domains: Vec<DomainSummary>,
+    pub recent: Vec<Attempt>,
+    pub due_reviews: usize,
+}
+
+impl UserSummary {
+    pub fn accuracy(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.correct as f64 / self.attempts as f64
+        }
+    }
+
+    /// Domains with the lowest accuracy, ignoring ones with too few attempts to judge.
+    pub fn weakest_domains(&self, count: usize) -> Vec<&DomainSummary> {
+        let mut domains: Vec<&DomainSummary> = self.domains.iter().filter(|d| d.attempts >= 3).collect();
+        domains.sort_by(|a, b| a.accuracy().total_cmp(&b.accuracy()));
+        domains.truncate(count);
+        domains
+    }
+}
+
+pub fn summarize_user(data: &StoreData, user_id: &str, now: u64) -> UserSummary {
+    let attempts: Vec<&Attempt> = data.attempts.iter().filter(|a| a.user_id == user_id).collect();
+
+    let mut by_domain: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for attempt in &attempts {
+        let entry = by_domain.entry(attempt.domain.as_str()).or_default();
+        entry.0 += 1;
+        if attempt.correct {
+            entry.1 += 1;
+        }
+    }
+
+    UserSummary {
+        attempts: attempts.len(),
+        correct: attempts.iter().filter(|a| a.correct).count(),
+        streak_days: user_streak(data, user_id, now).current,
+        domains: by_domain
+            .into_iter()
+            .filter(|(domain, _)| !domain.is_empty())
+            .map(|(domain, (attempts, correct))| DomainSummary {
+                domain: domain.to_string(),
+                attempts,
+                correct,
+                rating: user_rating(data, user_id, domain).rating,
+            })
+            .collect(),
+        recent: attempts.iter().rev().take(RECENT_ATTEMPTS).map(|a| (*a).clone()).collect(),
+        due_reviews: due_items(data, user_id, now).len(),
+    }
+}
+
+/// `/sat stats`: the caller's totals, per-domain ratings and achievements, just for them.
+pub async fn start_stats(state: &AppState, command: &SlackSlashCommand) -> Value {
+    let user_id = command.user_id.as_str();
+    let (summary, (earned, remaining)) = state
+        .store
+        .read(|data| (summarize_user(data, user_id, now_unix()), achievement_lines(data, &state.config, user_id)))
+        .await;
+
+    let mut text = format!(
+        "*📊 Your SAT stats*\n*Answered:* {}   *Accuracy:* {:.0}%   *Streak:* 🔥 {} day{}",
+        summary.attempts,
+        summary.accuracy() * 100.0,
+        summary.streak_days,
+        if summary.streak_days == 1 { "" } else { "s" }
+    );
+    for domain in &summary.domains {
+        text.push_str(&format!(
+            "\n• *{}* — {}/{} correct, rating {:.0}",
+            domain.domain, domain.correct, domain.attempts, domain.rating
+        ));
+    }
+    if state.config.features.achievements {
+        text.push_str(&format!("\n\n*🏅 Achievements* ({} still locked)", remaining));
+        if earned.is_empty() {
+            text.push_str("\nNone yet — answer a question correctly to earn your first.");
+        } else {
+            text.push('\n');
+            text.push_str(&earned.join("\n"));
+        }
+    }
+
+    json!({ "response_type": "ephemeral", "text": text })
+}
@@ -0,0 +1,429 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, TryLockError},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::RwLock;
+use crate::models::SATQuestion;
+
+/// A graded answer from any mode: channel questions, duels, practice modules or adaptive play.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    pub user_id: String,
+    #[serde(default)]
+    pub channel_id: String,
+    pub question_id: String,
+    pub domain: String,
+    pub difficulty: String,
+    pub selected: String,
+    pub correct: bool,
+    /// Unix seconds.
+    pub timestamp: u64,
+    pub source: String,
+    /// The message the answer was given on, so retries on the same post can be told apart.
+    #[serde(default)]
+    pub message_ts: Option<String>,
+    /// Hints revealed before answering; each one lowers the points a correct answer earns.
+    #[serde(default)]
+    pub hints: u8,
+}
+
+#[cfg(test)]
+impl Attempt {
+    /// A wrong answer to `question_id` in channel `C1`, for tests to adjust with the setters below.
+    pub fn test(user_id: &str, question_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            channel_id: "C1".to_string(),
+            question_id: question_id.to_string(),
+            domain: "Algebra".to_string(),
+            difficulty: "Medium".to_string(),
+            selected: "B".to_string(),
+            correct: false,
+            timestamp: 0,
+            source: "channel".to_string(),
+            message_ts: None,
+            hints: 0,
+        }
+    }
+
+    /// Picks `A` when correct and `B` otherwise.
+    pub fn correct(mut self, correct: bool) -> Self {
+        self.selected = if correct { "A" } else { "B" }.to_string();
+        self.correct = correct;
+        self
+    }
+
+    pub fn at(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn on_message(mut self, message_ts: &str) -> Self {
+        self.message_ts = Some(message_ts.to_string());
+        self
+    }
+
+    pub fn in_channel(mut self, channel_id: &str) -> Self {
+        self.channel_id = channel_id.to_string();
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: &str) -> Self {
+        self.difficulty = difficulty.to_string();
+        self
+    }
+
+    pub fn hints(mut self, hints: u8) -> Self {
+        self.hints = hints;
+        self
+    }
+}
+
+/// What the bot knows about a question without refetching the bank.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionStats {
+    pub domain: String,
+    pub difficulty: String,
+    pub rating: f64,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub correct: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserRating {
+    pub rating: f64,
+    pub games: u32,
+}
+
+/// SM-2 scheduling state for one question in one user's review queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub question_id: String,
+    pub ease: f64,
+    pub interval_days: u32,
+    pub repetitions: u32,
+    /// Unix seconds.
+    pub due: u64,
+}
+
+/// A question shown to a user or posted in a channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeenQuestion {
+    pub question_id: String,
+    /// Unix seconds.
+    pub at: u64,
+}
+
+/// A user's report that a question is broken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub id: String,
+    pub question_id: String,
+    pub user_id: String,
+    pub channel_id: String,
+    pub reason: String,
+    #[serde(default)]
+    pub details: String,
+    /// Unix seconds.
+    pub created_at: u64,
+    /// `open`, `resolved` or `dismissed`.
+    pub status: String,
+    #[serde(default)]
+    pub resolved_by: Option<String>,
+}
+
+/// Moderator corrections applied to upstream questions whenever the bank is loaded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuestionOverride {
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub correct_answer: Option<String>,
+    pub updated_by: String,
+    /// Unix seconds.
+    pub updated_at: u64,
+}
+
+/// A question written by a tutor through `/sat author`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomQuestion {
+    pub question: SATQuestion,
+    pub author: String,
+    /// `pending`, `approved` or `rejected`.
+    pub status: String,
+    /// Unix seconds.
+    pub created_at: u64,
+    #[serde(default)]
+    pub reviewed_by: Option<String>,
+}
+
+/// Per-channel behavior set with `/sat config`. Empty lists allow everything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub sections: Vec<String>,
+    pub domains: Vec<String>,
+    pub difficulties: Vec<String>,
+    /// `unlimited` or `single`.
+    pub attempt_policy: String,
+    /// `never`, `after_correct` or `after_answer`.
+    pub reveal_policy: String,
+    /// Minutes after posting that answers are still accepted.
+    pub answer_deadline_minutes: Option<u64>,
+    /// Whether ratings and standings are shown in the channel.
+    pub leaderboard: bool,
+    pub updated_by: String,
+    /// Unix seconds.
+    pub updated_at: u64,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self {
+            sections: Vec::new(),
+            domains: Vec::new(),
+            difficulties: Vec::new(),
+            attempt_policy: "unlimited".to_string(),
+            reveal_policy: "never".to_string(),
+            answer_deadline_minutes: None,
+            leaderboard: true,
+            updated_by: String::new(),
+            updated_at: 0,
+        }
+    }
+}
+
+/// Per-user streak bookkeeping. The streak itself is always worked out from attempts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreakState {
+    /// IANA name from `users.info`, for display.
+    pub timezone: Option<String>,
+    /// Seconds east of UTC, from `users.info`.
+    pub tz_offset: i64,
+    /// Unix seconds; the timezone is refreshed once a day.
+    pub tz_checked_at: u64,
+    /// Local time, in minutes after midnight, for the "streak at risk" DM. `None` turns it off.
+    pub reminder_minutes: Option<u32>,
+    /// Days since the epoch in the user's timezone, like every day number here.
+    pub last_reminded_day: Option<i64>,
+    pub freezes: u32,
+    /// Missed local days a freeze covered.
+    pub frozen_days: Vec<i64>,
+    pub last_freeze_earned_day: Option<i64>,
+    /// Highest milestone announced for the streak that began on `milestone_streak_start`.
+    pub milestone: u32,
+    pub milestone_streak_start: Option<i64>,
+}
+
+/// How far a user got through the hints on one posted question.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HintUsage {
+    pub user_id: String,
+    pub question_id: String,
+    pub message_ts: Option<String>,
+    /// 1 to `hints::MAX_HINTS`
+    pub level: u8,
+    pub used_at: u64,
+}
+
+/// A cohort competing in a [`Competition`]: its listed users, plus anyone answering in its channels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Team {
+    pub name: String,
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Competition {
+    pub id: String,
+    pub name: String,
+    pub teams: Vec<Team>,
+    /// Unix seconds; answers from `starts_at` up to `ends_at` count.
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub created_by: String,
+    /// Where the competition was set up; it gets the standings too.
+    pub channel_id: String,
+    /// "active" or "finished"
+    pub status: String,
+    /// How many weekly standings have gone out.
+    #[serde(default)]
+    pub weeks_posted: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarnedAchievement {
+    pub id: String,
+    /// Unix seconds.
+    pub earned_at: u64,
+}
+
+/// A practice module in progress. Saved on every change so its `practice_deadline` job can still
+/// submit it after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PracticeSession {
+    pub id: String,
+    pub user_id: String,
+    pub questions: Vec<SATQuestion>,
+    pub answers: Vec<Option<String>>,
+    pub flagged: HashSet<usize>,
+    /// Unix seconds.
+    pub started_at: u64,
+    pub time_limit_secs: u64,
+}
+
+/// Deferred work that has to survive a restart, run by the scheduler once `run_at` passes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: String,
+    /// What to run: "practice_deadline" or "question_close".
+    pub kind: String,
+    /// The thing the job acts on, e.g. a practice session id.
+    pub target: String,
+    pub user_id: String,
+    /// Unix seconds.
+    pub run_at: u64,
+    /// For jobs about a channel message: where it is and the thread to reply in.
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub message_ts: Option<String>,
+    #[serde(default)]
+    pub thread_ts: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StoreData {
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+    /// user id -> domain -> rating
+    #[serde(default)]
+    pub user_ratings: HashMap<String, HashMap<String, UserRating>>,
+    #[serde(default)]
+    pub questions: HashMap<String, QuestionStats>,
+    /// user id -> question id -> review item
+    #[serde(default)]
+    pub reviews: HashMap<String, HashMap<String, ReviewItem>>,
+    /// Users who opted in to the daily review reminder, with the last day (unix days) they were reminded.
+    #[serde(default)]
+    pub review_reminders: HashMap<String, Option<u64>>,
+    /// channel id -> questions posted there
+    #[serde(default)]
+    pub channel_history: HashMap<String, Vec<SeenQuestion>>,
+    /// user id -> questions served to them
+    #[serde(default)]
+    pub user_history: HashMap<String, Vec<SeenQuestion>>,
+    #[serde(default)]
+    pub reports: Vec<Report>,
+    /// question id -> override
+    #[serde(default)]
+    pub question_overrides: HashMap<String, QuestionOverride>,
+    #[serde(default)]
+    pub custom_questions: Vec<CustomQuestion>,
+    /// channel id -> settings
+    #[serde(default)]
+    pub channel_settings: HashMap<String, ChannelSettings>,
+    #[serde(default)]
+    pub scheduled_jobs: Vec<ScheduledJob>,
+    /// session id -> unfinished practice module
+    #[serde(default)]
+    pub practice_sessions: HashMap<String, PracticeSession>,
+    /// user id -> streak bookkeeping
+    #[serde(default)]
+    pub streaks: HashMap<String, StreakState>,
+    /// user id -> achievements earned, oldest first
+    #[serde(default)]
+    pub achievements: HashMap<String, Vec<EarnedAchievement>>,
+    #[serde(default)]
+    pub competitions: Vec<Competition>,
+    /// Users who opted in to the weekly digest DM, with the last week (see `digest::week_index`) it went out.
+    #[serde(default)]
+    pub digest_subscribers: HashMap<String, Option<u64>>,
+    /// The last week channel digests were posted.
+    #[serde(default)]
+    pub last_channel_digest: Option<u64>,
+    #[serde(default)]
+    pub hints: Vec<HintUsage>,
+}
+
+/// JSON-file backed storage. Every update is written through to disk.
+#[derive(Clone)]
+pub struct Store {
+    path: PathBuf,
+    data: Arc<RwLock<StoreData>>,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read store {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse store {}", path.display()))?
+        } else {
+            StoreData::default()
+        };
+
+        Ok(Self {
+            path,
+            data: Arc::new(RwLock::new(data)),
+        })
+    }
+
+    pub async fn read<R>(&self, f: impl FnOnce(&StoreData) -> R) -> R {
+        f(&*self.data.read().await)
+    }
+
+    pub async fn update<R>(&self, f: impl FnOnce(&mut StoreData) -> R) -> Result<R> {
+        let mut data = self.data.write().await;
+        let result = f(&mut data);
+        let contents = serde_json::to_string(&*data)?;
+
+        // Write-then-rename so a crash mid-write never leaves a truncated file behind.
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, contents)
+            .await
+            .with_context(|| format!("Failed to write store {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+
+        Ok(result)
+    }
+
+    /// Takes an exclusive lock on `<store>.lock`, held until the returned file is dropped. The
+    /// server holds it while running so a CLI write can't be overwritten by the server's copy of
+    /// the data. The lock is released by the OS, so a crash never leaves it stuck.
+    pub fn lock(&self) -> Result<File> {
+        let path = self.path.with_extension("lock");
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(file),
+            Err(TryLockError::WouldBlock) => bail!(
+                "{} is in use by a running server; stop it first, or its next write will discard this change",
+                self.path.display()
+            ),
+            Err(TryLockError::Error(e)) => Err(e).with_context(|| format!("Failed to lock {}", path.display())),
+        }
+    }
+
+    /// Writes and removes a probe file next to the store, so readiness fails on a full or
+    /// read-only disk before an update does.
+    pub async fn check_writable(&self) -> Result<()> {
+        let probe = self.path.with_extension("probe");
+        tokio::fs::write(&probe, b"ok")
+            .await
+            .with_context(|| format!("Failed to write {}", probe.display()))?;
+        tokio::fs::remove_file(&probe).await?;
+        Ok(())
+    }
+}
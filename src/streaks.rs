@@ -0,0 +1,396 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use crate::{
+    models::SlackSlashCommand,
+    slack::{post_message, section_block, slack_api},
+    state::AppState,
+    store::{StoreData, StreakState},
+    utils::now_unix,
+};
+
+const DAY: i64 = 24 * 60 * 60;
+/// Streak lengths worth telling the channel about.
+const MILESTONES: [u32; 8] = [3, 7, 14, 30, 50, 100, 200, 365];
+/// A freeze is earned every this many streak days...
+const FREEZE_EVERY_DAYS: u32 = 7;
+/// ...up to this many banked at once.
+const MAX_FREEZES: u32 = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Streak {
+    pub current: u32,
+    pub best: u32,
+    /// Whether today (local) already has a correct answer.
+    pub done_today: bool,
+}
+
+/// Days since the epoch in a timezone `tz_offset` seconds east of UTC.
+pub fn local_day(timestamp: u64, tz_offset: i64) -> i64 {
+    (timestamp as i64 + tz_offset).div_euclid(DAY)
+}
+
+/// The user's streak: consecutive local days, ending today or yesterday, with a correct answer
+/// or a spent freeze.
+pub fn user_streak(data: &StoreData, user_id: &str, now: u64) -> Streak {
+    let state = data.streaks.get(user_id).cloned().unwrap_or_default();
+    let days = covered_days(data, user_id, &state);
+    let today = local_day(now, state.tz_offset);
+    streak_from_days(&days, today)
+}
+
+fn covered_days(data: &StoreData, user_id: &str, state: &StreakState) -> BTreeSet<i64> {
+    data.attempts
+        .iter()
+        .filter(|a| a.user_id == user_id && a.correct)
+        .map(|a| local_day(a.timestamp, state.tz_offset))
+        .chain(state.frozen_days.iter().copied())
+        .collect()
+}
+
+fn streak_from_days(days: &BTreeSet<i64>, today: i64) -> Streak {
+    let done_today = days.contains(&today);
+    let mut day = if done_today { today } else { today - 1 };
+    let mut current = 0;
+    while days.contains(&day) {
+        current += 1;
+        day -= 1;
+    }
+
+    let mut best = 0;
+    let mut run = 0;
+    let mut previous = None;
+    for &day in days.iter().filter(|&&d| d <= today) {
+        run = if previous == Some(day - 1) { run + 1 } else { 1 };
+        best = best.max(run);
+        previous = Some(day);
+    }
+
+    Streak { current, best, done_today }
+}
+
+/// Handles `/sat streak`, `/sat streak remind HH:MM` and `/sat streak remind off`.
+pub async fn start_streak(state: &AppState, command: &SlackSlashCommand, args: &str) -> Value {
+    let user_id = command.user_id.as_str();
+    let mut words = args.split_whitespace();
+    if words.next().is_some_and(|w| w.eq_ignore_ascii_case("remind")) {
+        let reminder = match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("off") => None,
+            Some(time) => match parse_time(time) {
+                Some(minutes) => Some(minutes),
+                None => {
+                    return json!({
+                        "response_type": "ephemeral",
+                        "text": "Usage: `/sat streak remind HH:MM` (24-hour, your local time) or `/sat streak remind off`.",
+                    })
+                }
+            },
+            None => Some(19 * 60),
+        };
+
+        if let Err(e) = refresh_timezone(state, user_id).await {
+            tracing::warn!("Failed to look up timezone for {}: {}", user_id, e);
+        }
+        let result = state
+            .store
+            .update(|data| {
+                let streak = data.streaks.entry(user_id.to_string()).or_default();
+                streak.reminder_minutes = reminder;
+                streak.timezone.clone()
+            })
+            .await;
+        let text = match (result, reminder) {
+            (Err(e), _) => {
+                tracing::error!("Failed to update streak reminder: {}", e);
+                "Sorry, I couldn't save that setting.".to_string()
+            }
+            (Ok(timezone), Some(minutes)) => format!(
+                "⏰ If your streak is at risk, I'll DM you at {:02}:{:02} ({}).",
+                minutes / 60,
+                minutes % 60,
+                timezone.as_deref().unwrap_or("UTC")
+            ),
+            (Ok(_), None) => "🔕 Streak reminders turned off.".to_string(),
+        };
+        return json!({ "response_type": "ephemeral", "text": text });
+    }
+
+    let now = now_unix();
+    let (streak, freezes) = state
+        .store
+        .read(|data| {
+            let freezes = data.streaks.get(user_id).map(|s| s.freezes).unwrap_or(0);
+            (user_streak(data, user_id, now), freezes)
+        })
+        .await;
+    let today = if streak.done_today {
+        "✅ Today's done."
+    } else if streak.current > 0 {
+        "⚠️ Answer one question correctly today to keep it going."
+    } else {
+        "Answer a question correctly to start one."
+    };
+    json!({
+        "response_type": "ephemeral",
+        "text": format!(
+            "🔥 *Streak:* {} day{}   *Best:* {}   🧊 *Freezes:* {}/{}\n{}\nFreezes are earned every {} days and cover a missed day automatically.",
+            streak.current,
+            if streak.current == 1 { "" } else { "s" },
+            streak.best,
+            freezes,
+            MAX_FREEZES,
+            today,
+            FREEZE_EVERY_DAYS
+        ),
+    })
+}
+
+/// `HH:MM`, 24-hour, as minutes after midnight.
+fn parse_time(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Fetches the user's timezone from `users.info` if it's unknown or more than a day old.
+async fn refresh_timezone(state: &AppState, user_id: &str) -> Result<()> {
+    let now = now_unix();
+    let stale = state
+        .store
+        .read(|data| data.streaks.get(user_id).is_none_or(|s| now.saturating_sub(s.tz_checked_at) > DAY as u64))
+        .await;
+    if !stale {
+        return Ok(());
+    }
+
+    let token = state.config.slack.bot_token.clone();
+    let info = slack_api(&token, "users.info", &json!({ "user": user_id })).await?;
+    let offset = info["user"]["tz_offset"].as_i64().unwrap_or(0);
+    let timezone = info["user"]["tz"].as_str().map(str::to_string);
+    state
+        .store
+        .update(|data| {
+            let streak = data.streaks.entry(user_id.to_string()).or_default();
+            streak.tz_offset = offset;
+            streak.timezone = timezone;
+            streak.tz_checked_at = now;
+        })
+        .await?;
+    Ok(())
+}
+
+/// Updates the user's streak in the background after a correct answer.
+pub fn track_correct_answer(state: &AppState, user_id: &str, channel_id: Option<&str>) {
+    if !state.config.features.streaks {
+        return;
+    }
+    let user_id = user_id.to_string();
+    let channel_id = channel_id.map(str::to_string);
+    state.spawn(move |state| async move {
+        if let Err(e) = after_answer(&state, &user_id, channel_id.as_deref()).await {
+            tracing::error!("Failed to update streak for {}: {}", user_id, e);
+        }
+    });
+}
+
+/// Runs after a graded answer: banks a freeze every [`FREEZE_EVERY_DAYS`] and announces
+/// milestones in `channel_id`, or by DM when the answer wasn't given in a channel.
+pub async fn after_answer(state: &AppState, user_id: &str, channel_id: Option<&str>) -> Result<()> {
+    if let Err(e) = refresh_timezone(state, user_id).await {
+        tracing::warn!("Failed to look up timezone for {}, using the last known one: {}", user_id, e);
+    }
+    let now = now_unix();
+    let (streak, earned, milestone) = state
+        .store
+        .update(|data| {
+            let streak = user_streak(data, user_id, now);
+            let entry = data.streaks.entry(user_id.to_string()).or_default();
+            let today = local_day(now, entry.tz_offset);
+            if !streak.done_today {
+                return (streak, false, None);
+            }
+
+            let earned = streak.current.is_multiple_of(FREEZE_EVERY_DAYS)
+                && entry.last_freeze_earned_day != Some(today)
+                && entry.freezes < MAX_FREEZES;
+            if earned {
+                entry.freezes += 1;
+                entry.last_freeze_earned_day = Some(today);
+            }
+
+            let started = today - streak.current as i64 + 1;
+            if entry.milestone_streak_start != Some(started) {
+                entry.milestone_streak_start = Some(started);
+                entry.milestone = 0;
+            }
+            let milestone = MILESTONES
+                .into_iter()
+                .filter(|&m| m <= streak.current && m > entry.milestone)
+                .max();
+            if let Some(m) = milestone {
+                entry.milestone = m;
+            }
+            (streak, earned, milestone)
+        })
+        .await?;
+
+    let token = state.config.slack.bot_token.clone();
+    if let Some(days) = milestone {
+        let text = format!("🔥 <@{}> just hit a *{}-day* SAT streak!", user_id, days);
+        let target = channel_id.filter(|c| !c.is_empty()).unwrap_or(user_id);
+        post_message(&token, target, vec![section_block(&text)]).await?;
+    }
+    if earned {
+        let text = format!(
+            "🧊 {} days in a row — you earned a streak freeze! It'll cover the next day you miss.",
+            streak.current
+        );
+        post_message(&token, user_id, vec![section_block(&text)]).await?;
+    }
+    Ok(())
+}
+
+/// Spends freezes on days that just ended unanswered, then DMs users whose streak is at risk
+/// once their chosen local time has passed.
+pub async fn send_streak_reminders(state: &AppState) -> Result<()> {
+    let now = now_unix();
+    let (frozen, at_risk) = state
+        .store
+        .update(|data| {
+            let users: Vec<String> = data.streaks.keys().cloned().collect();
+            let mut frozen = Vec::new();
+            let mut at_risk = Vec::new();
+            for user_id in users {
+                let mut entry = data.streaks[&user_id].clone();
+                let mut days = covered_days(data, &user_id, &entry);
+                let today = local_day(now, entry.tz_offset);
+                let yesterday = today - 1;
+
+                // Yesterday went unanswered but the streak was alive the day before.
+                if entry.freezes > 0 && !days.contains(&yesterday) && days.contains(&(yesterday - 1)) {
+                    entry.freezes -= 1;
+                    entry.frozen_days.push(yesterday);
+                    days.insert(yesterday);
+                    frozen.push((user_id.clone(), entry.freezes));
+                }
+
+                let streak = streak_from_days(&days, today);
+                let minutes_now = ((now as i64 + entry.tz_offset).rem_euclid(DAY) / 60) as u32;
+                if let Some(reminder) = entry.reminder_minutes {
+                    if minutes_now >= reminder
+                        && entry.last_reminded_day != Some(today)
+                        && streak.current > 0
+                        && !streak.done_today
+                    {
+                        entry.last_reminded_day = Some(today);
+                        at_risk.push((user_id.clone(), streak.current));
+                    }
+                }
+                data.streaks.insert(user_id, entry);
+            }
+            (frozen, at_risk)
+        })
+        .await?;
+
+    let token = state.config.slack.bot_token.clone();
+    for (user_id, left) in frozen {
+        let text = format!(
+            "🧊 You missed yesterday, so a streak freeze kept your streak alive. {} freeze{} left.",
+            left,
+            if left == 1 { "" } else { "s" }
+        );
+        if let Err(e) = post_message(&token, &user_id, vec![section_block(&text)]).await {
+            tracing::error!("Failed to tell {} about a spent freeze: {}", user_id, e);
+        }
+    }
+    for (user_id, current) in at_risk {
+        let text = format!(
+            "🔥 Your {}-day streak ends at midnight! Answer one question correctly with `/sat` to keep it.",
+            current
+        );
+        if let Err(e) = post_message(&token, &user_id, vec![section_block(&text)]).await {
+            tracing::error!("Failed to send streak reminder to {}: {}", user_id, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Attempt;
+
+    const TODAY: i64 = 19_700;
+
+    fn days(list: &[i64]) -> BTreeSet<i64> {
+        list.iter().map(|d| TODAY + d).collect()
+    }
+
+    fn correct_at(timestamp: u64) -> Attempt {
+        Attempt::test("U1", "q1").correct(true).at(timestamp)
+    }
+
+    #[test]
+    fn local_day_shifts_with_the_offset() {
+        let midnight = (TODAY * DAY) as u64;
+        assert_eq!(local_day(midnight, 0), TODAY);
+        assert_eq!(local_day(midnight - 1, 0), TODAY - 1);
+        // 23:30 UTC is already tomorrow in UTC+1 and still today in UTC-5.
+        let late = midnight + 23 * 3600 + 1800;
+        assert_eq!(local_day(late, 3600), TODAY + 1);
+        assert_eq!(local_day(late, -5 * 3600), TODAY);
+        // 02:00 UTC is still yesterday in UTC-5.
+        assert_eq!(local_day(midnight + 2 * 3600, -5 * 3600), TODAY - 1);
+    }
+
+    #[test]
+    fn streak_counts_back_from_today() {
+        let streak = streak_from_days(&days(&[-2, -1, 0]), TODAY);
+        assert_eq!(streak, Streak { current: 3, best: 3, done_today: true });
+    }
+
+    #[test]
+    fn streak_survives_until_today_is_over() {
+        let streak = streak_from_days(&days(&[-2, -1]), TODAY);
+        assert_eq!(streak, Streak { current: 2, best: 2, done_today: false });
+    }
+
+    #[test]
+    fn missed_day_breaks_the_streak_but_keeps_the_best() {
+        let streak = streak_from_days(&days(&[-6, -5, -4, -3, -1, 0]), TODAY);
+        assert_eq!(streak, Streak { current: 2, best: 4, done_today: true });
+        assert_eq!(streak_from_days(&days(&[-3]), TODAY).current, 0);
+    }
+
+    #[test]
+    fn future_days_are_ignored_for_best() {
+        let streak = streak_from_days(&days(&[0, 1, 2, 3]), TODAY);
+        assert_eq!(streak, Streak { current: 1, best: 1, done_today: true });
+    }
+
+    #[test]
+    fn frozen_days_bridge_a_gap() {
+        let mut data = StoreData::default();
+        for offset in [-3, -1, 0] {
+            data.attempts.push(correct_at(((TODAY + offset) * DAY + 12 * 3600) as u64));
+        }
+        let now = (TODAY * DAY + 18 * 3600) as u64;
+        assert_eq!(user_streak(&data, "U1", now).current, 2);
+
+        data.streaks.insert("U1".to_string(), StreakState { frozen_days: vec![TODAY - 2], ..StreakState::default() });
+        let streak = user_streak(&data, "U1", now);
+        assert_eq!(streak, Streak { current: 4, best: 4, done_today: true });
+    }
+
+    #[test]
+    fn user_streak_uses_the_stored_offset() {
+        let mut data = StoreData::default();
+        // 01:00 UTC on TODAY is 20:00 yesterday in UTC-5.
+        data.attempts.push(correct_at((TODAY * DAY + 3600) as u64));
+        data.streaks.insert("U1".to_string(), StreakState { tz_offset: -5 * 3600, ..StreakState::default() });
+        let now = (TODAY * DAY + 3 * 3600) as u64;
+        assert_eq!(user_streak(&data, "U1", now), Streak { current: 1, best: 1, done_today: true });
+        let noon_tomorrow = ((TODAY + 1) * DAY + 17 * 3600) as u64;
+        assert_eq!(user_streak(&data, "U1", noon_tomorrow), Streak { current: 0, best: 1, done_today: false });
+    }
+}
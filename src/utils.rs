@@ -1,7 +1,8 @@
 use axum::http::{HeaderMap, StatusCode};
 use hmac::{Hmac, Mac};
+use rand::Rng;
 use sha2::Sha256;
-use std::{env, time::{SystemTime, UNIX_EPOCH}};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn format_text_for_slack(text: &str) -> String {
     let mut formatted = text
@@ -48,62 +49,93 @@ pub fn format_text_for_slack(text: &str) -> String {
     formatted
 }
 
-pub async fn verify_slack_signature(
-    headers: &HeaderMap,
-    body: &str,
-) -> Result<(), StatusCode> {
+pub fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Parses `YYYY-MM-DD` (as sent by Slack datepickers) to unix seconds at UTC midnight.
+pub fn parse_date(text: &str) -> Option<u64> {
+    let mut parts = text.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil date, after Howard Hinnant's algorithm.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400).ok()
+}
+
+/// Short random identifier used to key in-memory sessions from button values.
+pub fn generate_id() -> String {
+    format!("{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+/// Extracts the user ID from an escaped mention such as `<@U123ABC|jane>` or `<@U123ABC>`.
+pub fn parse_user_mention(text: &str) -> Option<String> {
+    let start = text.find("<@")? + 2;
+    let rest = &text[start..];
+    let end = rest.find(['>', '|'])?;
+    let id = &rest[..end];
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// Checks Slack's `v0` request signature over the raw body and rejects requests more than five
+/// minutes old, so a captured request can't be replayed.
+pub fn verify_slack_signature(headers: &HeaderMap, body: &[u8], signing_secret: &str) -> Result<(), StatusCode> {
     let timestamp = headers
         .get("x-slack-request-timestamp")
         .and_then(|h| h.to_str().ok())
         .and_then(|s| s.parse::<u64>().ok())
         .ok_or_else(|| {
-            tracing::error!("Missing or invalid timestamp header");
-            StatusCode::BAD_REQUEST
+            tracing::warn!("Missing or invalid timestamp header");
+            StatusCode::UNAUTHORIZED
         })?;
 
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
-    
-    tracing::debug!("Timestamp validation: now={}, request_time={}, diff={}", now, timestamp, now.abs_diff(timestamp));
-    
     if now.abs_diff(timestamp) > 300 {
-        tracing::error!("Request timestamp too far from current time: {} (now: {})", timestamp, now);
-        return Err(StatusCode::BAD_REQUEST);
+        tracing::warn!("Request timestamp too far from current time: {} (now: {})", timestamp, now);
+        return Err(StatusCode::UNAUTHORIZED);
     }
 
-    let signing_secret = env::var("SLACK_SIGNING_SECRET").map_err(|e| {
-        tracing::error!("Failed to get SLACK_SIGNING_SECRET: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let sig_basestring = format!("v0:{}:{}", timestamp, body);
-    tracing::debug!("Signature base string: {}", sig_basestring);
-    
-    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes())
-        .map_err(|e| {
-            tracing::error!("Failed to create HMAC: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    mac.update(sig_basestring.as_bytes());
-    let expected_signature = format!("v0={}", hex::encode(mac.finalize().into_bytes()));
-
-    let slack_signature = headers
+    let signature = headers
         .get("x-slack-signature")
         .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("v0="))
+        .and_then(|s| hex::decode(s).ok())
         .ok_or_else(|| {
-            tracing::error!("Missing Slack signature header");
-            StatusCode::BAD_REQUEST
+            tracing::warn!("Missing or malformed Slack signature header");
+            StatusCode::UNAUTHORIZED
         })?;
 
-    tracing::debug!("Expected signature: {}", expected_signature);
-    tracing::debug!("Received signature: {}", slack_signature);
-
-    if slack_signature != expected_signature {
-        tracing::error!("Signature mismatch");
-        return Err(StatusCode::UNAUTHORIZED);
-    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_secret.as_bytes()).map_err(|e| {
+        tracing::error!("Failed to create HMAC: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+    // Constant-time comparison.
+    mac.verify_slice(&signature).map_err(|_| {
+        tracing::warn!("Signature mismatch");
+        StatusCode::UNAUTHORIZED
+    })
+}
 
-    Ok(())
-} 
\ No newline at end of file
+/// Float comparison for tests of rating and statistics math.
+#[cfg(test)]
+pub fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
@@ -0,0 +1,106 @@
+use slack_sat_bot::{
+    models::{SATQuestion, Visuals},
+    slack::{create_question_content_blocks, parse_question_bank},
+};
+
+const BANK: &str = include_str!("fixtures/upstream_bank.json");
+const SINGLE: &str = include_str!("fixtures/single_question.json");
+
+fn bank() -> Vec<SATQuestion> {
+    parse_question_bank(BANK).expect("upstream bank parses")
+}
+
+fn question(id: &str) -> SATQuestion {
+    bank().into_iter().find(|q| q.id == id).expect("fixture question exists")
+}
+
+#[test]
+fn parses_every_question_in_the_bank_payload() {
+    let ids: Vec<String> = bank().into_iter().map(|q| q.id).collect();
+    assert_eq!(ids, ["missing-fields", "explicit-nulls", "empty-strings", "null-visuals", "with-svg"]);
+}
+
+#[test]
+fn missing_null_and_empty_paragraphs_are_none() {
+    for id in ["missing-fields", "explicit-nulls", "empty-strings"] {
+        assert_eq!(question(id).question.paragraph, None, "{}", id);
+    }
+}
+
+#[test]
+fn paragraph_text_is_kept_verbatim() {
+    assert_eq!(
+        question("null-visuals").question.paragraph.as_deref(),
+        Some("A right triangle has legs of length 3 and 4.")
+    );
+    // A paragraph that genuinely says "null" is text, not a missing value.
+    assert_eq!(question("with-svg").question.paragraph.as_deref(), Some("null"));
+}
+
+#[test]
+fn missing_null_and_empty_visuals_are_none() {
+    for id in ["missing-fields", "explicit-nulls", "empty-strings", "null-visuals"] {
+        assert_eq!(question(id).visuals, None, "{}", id);
+    }
+}
+
+#[test]
+fn svg_visuals_are_parsed() {
+    let visuals = question("with-svg").visuals.expect("visuals present");
+    assert_eq!(visuals.visual_type.as_deref(), Some("svg"));
+    assert!(visuals.svg_content.is_some_and(|svg| svg.starts_with("<svg")));
+}
+
+#[test]
+fn single_question_fallback_shape_parses() {
+    let bank = parse_question_bank(SINGLE).expect("single question parses");
+    assert_eq!(bank.len(), 1);
+    assert_eq!(bank[0].id, "single");
+    assert_eq!(bank[0].question.paragraph, None);
+    // A bare placeholder string in place of the visuals object means no visuals.
+    assert_eq!(bank[0].visuals, None);
+}
+
+#[test]
+fn unrecognised_payload_is_an_error() {
+    assert!(parse_question_bank(r#"{"reading": []}"#).is_err());
+    assert!(parse_question_bank("not json").is_err());
+}
+
+#[test]
+fn serialization_omits_absent_fields_and_round_trips() {
+    for original in bank() {
+        let json = serde_json::to_value(&original).unwrap();
+        if original.question.paragraph.is_none() {
+            assert!(json["question"].get("paragraph").is_none(), "{}", original.id);
+        }
+        if original.visuals.is_none() {
+            assert!(json.get("visuals").is_none(), "{}", original.id);
+        }
+
+        let restored: SATQuestion = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.question.paragraph, original.question.paragraph, "{}", original.id);
+        assert_eq!(restored.visuals, original.visuals, "{}", original.id);
+    }
+}
+
+#[test]
+fn visuals_with_only_one_field_are_kept() {
+    let visuals: Visuals = serde_json::from_str(r#"{ "type": "svg" }"#).unwrap();
+    assert_eq!(visuals.visual_type.as_deref(), Some("svg"));
+    assert_eq!(visuals.svg_content, None);
+    assert!(!visuals.is_empty());
+}
+
+#[test]
+fn paragraph_block_follows_the_option() {
+    let has_paragraph_block = |q: &SATQuestion| {
+        create_question_content_blocks(q)
+            .iter()
+            .any(|b| b.text.as_ref().is_some_and(|t| t.text.starts_with("*Paragraph:*")))
+    };
+    assert!(!has_paragraph_block(&question("missing-fields")));
+    assert!(!has_paragraph_block(&question("empty-strings")));
+    assert!(has_paragraph_block(&question("null-visuals")));
+    assert!(has_paragraph_block(&question("with-svg")));
+}